@@ -15,43 +15,54 @@
  * You should have received a copy of the MIT License
  * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
  */
-mod math;
+use slope_math::{self as math, PoolState, TryMath};
 
-use std::fmt::Write;
 use alkanes_runtime::{
-    println,
-    stdio::stdout,
     message::MessageDispatch,
     runtime::AlkaneResponder,
     storage::StoragePointer,
 };
 use alkanes_support::{
+    cellpack::Cellpack,
     context::Context,
     id::AlkaneId,
     parcel::{AlkaneTransfer, AlkaneTransferParcel},
     response::CallResponse,
 };
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use metashrew_support::{
     byte_view::ByteView,
     compat::{to_arraybuffer_layout, to_passback_ptr},
     index_pointer::KeyValuePointer,
 };
 pub use ruint::aliases::U256;
+pub use slope_errors::SynthPoolError;
+pub use slope_events::SynthPoolEvent;
 use serde::{de::Visitor, de::MapAccess, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
 use std::ops::Deref;
 use std::sync::Arc;
 use std::fmt;
 use std::collections::HashMap;
 
-const N_COINS: u128 = 2;
+const N_COINS: u128 = 2; // default coin count for pools created through InitPool
+const MAX_COINS: u128 = 8;
 const PRECISION: u128 = 10u128.pow(18); // 1e18
 const FEE_DENOMINATOR: u128 = 10u128.pow(10);
-
-// aeBTC and frBTC
-const TOKEN_NAMES: [&str; 2] = ["æBTC", "frBTC"];
-
-#[derive(MessageDispatch)]
+const MAX_A: u128 = 10u128.pow(6) * 100; // in the same A_PRECISION units as InitPool's A
+const MAX_A_CHANGE: u128 = 10;
+const MIN_RAMP_BLOCKS: u64 = 144; // ~1 day
+const MAX_FEE: u128 = 5 * 10u128.pow(9); // 50%
+const MAX_ADMIN_FEE: u128 = 10u128.pow(10); // 100%
+const ADMIN_ACTIONS_DELAY: u64 = 3 * 144; // ~3 days
+const KILL_DEADLINE_BLOCKS: u64 = 2 * 30 * 144; // ~2 months
+const LP_DECIMALS: u128 = 8; // LP supply tracks D, which is normalized to 8 decimals
+const MAX_COIN_DECIMALS: u128 = 18;
+const DEFAULT_MA_HALF_LIFE: u64 = 6; // ~1 hour
+const MAX_MA_HALF_LIFE: u64 = 7 * 144; // ~1 week
+const OBSERVATION_SLOTS: u64 = 7 * 144; // at most one observation per block
+const COIN_VIEW_FUEL: u64 = 1_000_000; // most fuel a coin's symbol or rate view may burn
+
+#[derive(MessageDispatch, Debug)]
 pub enum SynthPoolMessage {
     #[opcode(0)]
     InitPool {
@@ -85,19 +96,282 @@ pub enum SynthPoolMessage {
        j: u128,
        min_dy: u128,
    },
+    /// Initialize a pool over 2..=8 coins, given as flattened (block, tx) pairs.
+    /// `coin_fees` sets the swap fee per output coin; leave it empty to charge `fee` on every coin.
+    /// `decimals` gives each coin's precision; leave it empty for 8 on every coin.
+    /// `rate_oracles` gives, per coin, an opcode the pool staticcalls on that coin
+    /// for its rate scaled by 1e18, or 0 for a fixed 1:1 rate; leave it empty for none.
+    #[opcode(6)]
+    InitPoolN {
+        coins: Vec<u128>,
+        A: u128,
+        fee: u128,
+        coin_fees: Vec<u128>,
+        decimals: Vec<u128>,
+        rate_oracles: Vec<u128>,
+        admin_fee: u128,
+        owner: AlkaneId,
+    },
+    // `deadline` works as in opcodes 31-35
+    #[opcode(7)]
+    SwapExactOut {
+        j: u128,
+        dy: u128,
+        max_dx: u128,
+        deadline: u128,
+    },
     #[opcode(10)]
     ClaimAdminFees,
+    #[opcode(11)]
+    RampA {
+        future_a: u128,
+        future_height: u128,
+    },
+    #[opcode(12)]
+    StopRampA,
+    #[opcode(13)]
+    /// `offpeg_fee_multiplier` scales fees up as the pool leaves balance, in
+    /// FEE_DENOMINATOR units; values up to FEE_DENOMINATOR turn it off
+    CommitNewFee {
+        fee: u128,
+        admin_fee: u128,
+        coin_fees: Vec<u128>,
+        offpeg_fee_multiplier: u128,
+    },
+    #[opcode(14)]
+    ApplyNewFee,
+    #[opcode(15)]
+    RevertNewParameters,
+    #[opcode(16)]
+    CommitTransferOwnership {
+        new_owner: AlkaneId,
+    },
+    #[opcode(17)]
+    ApplyTransferOwnership,
+    #[opcode(18)]
+    RevertTransferOwnership,
+    #[opcode(19)]
+    RenounceOwnership,
+    #[opcode(20)]
+    KillMe,
+    #[opcode(21)]
+    UnkillMe,
+    #[opcode(22)]
+    SetOracleHalfLife {
+        half_life: u128,
+    },
+    // Opcodes 1-5 followed by a `deadline`, the last block height at which
+    // the call may execute; 0 disables it
+    #[opcode(31)]
+    AddLiquidityWithDeadline {
+        min_mint_amount: u128,
+        deadline: u128,
+    },
+    #[opcode(32)]
+    RemoveLiquidityWithDeadline {
+        min_amounts: Vec<u128>,
+        deadline: u128,
+    },
+    #[opcode(33)]
+    RemoveLiquidityOneCoinWithDeadline {
+        i: u128,
+        min_amount: u128,
+        deadline: u128,
+    },
+    #[opcode(34)]
+    RemoveLiquidityImbalanceWithDeadline {
+        amounts: Vec<u128>,
+        max_burn_amount: u128,
+        deadline: u128,
+    },
+    #[opcode(35)]
+    SwapWithDeadline {
+        j: u128,
+        min_dy: u128,
+        deadline: u128,
+    },
     #[opcode(50)]
     Forward,
+    #[opcode(99)]
+    #[returns(String)]
+    GetName,
     #[opcode(100)]
+    #[returns(String)]
+    GetSymbol,
+    #[opcode(101)]
     #[returns(u128)]
+    GetTotalSupply,
+    // GetVirtualPrice, GetBalances and GetA answered opcodes 100-102 before
+    // the LP token took the standard alkane views (99 name, 100 symbol, 101
+    // total supply, 102 cap), so they moved to 200-202 and callers of the
+    // old opcodes must switch (see CHANGELOG.md). Their values are 32-byte
+    // little-endian U256s.
+    #[opcode(200)]
+    #[returns(U256)]
     GetVirtualPrice,
-    #[opcode(101)]
-    #[returns(u128, u128)]
+    #[opcode(201)]
+    #[returns(Vec<U256>)]
     GetBalances,
-    #[opcode(102)]
-    #[returns(u128)]
+    #[opcode(202)]
+    #[returns(U256)]
     GetA,
+    #[opcode(203)]
+    #[returns(u128, u128, u128, u128, Vec<u128>)]
+    GetPendingParameters,
+    #[opcode(204)]
+    #[returns(u128, u128)]
+    GetOwner,
+    #[opcode(205)]
+    #[returns(u128)]
+    IsKilled,
+    #[opcode(206)]
+    #[returns(u128)]
+    GetDy {
+        i: u128,
+        j: u128,
+        dx: u128,
+    },
+    #[opcode(207)]
+    #[returns(u128, u128, u128, u128)]
+    GetDyBreakdown {
+        i: u128,
+        j: u128,
+        dx: u128,
+    },
+    #[opcode(208)]
+    #[returns(u128)]
+    GetDx {
+        i: u128,
+        j: u128,
+        dy: u128,
+    },
+    #[opcode(209)]
+    #[returns(u128)]
+    GetSpotPrice {
+        i: u128,
+        j: u128,
+    },
+    #[opcode(210)]
+    #[returns(u128, u128, u128)]
+    GetPriceImpact {
+        i: u128,
+        j: u128,
+        dx: u128,
+    },
+    #[opcode(211)]
+    #[returns(u128)]
+    CalcTokenAmount {
+        amounts: Vec<u128>,
+        is_deposit: u128,
+    },
+    #[opcode(212)]
+    #[returns(u128, Vec<u128>)]
+    CalcTokenAmountWithFees {
+        amounts: Vec<u128>,
+        is_deposit: u128,
+    },
+    #[opcode(213)]
+    #[returns(u128)]
+    CalcWithdrawOneCoin {
+        token_amount: u128,
+        i: u128,
+    },
+    #[opcode(214)]
+    #[returns(u128, u128)]
+    CalcWithdrawOneCoinWithFee {
+        token_amount: u128,
+        i: u128,
+    },
+    /// EMA price of coin i in units of coin 0, scaled by 1e18
+    #[opcode(215)]
+    #[returns(u128)]
+    PriceOracle {
+        i: u128,
+    },
+    /// Spot price of coin i in units of coin 0 after the last state change, scaled by 1e18
+    #[opcode(216)]
+    #[returns(u128)]
+    LastPrice {
+        i: u128,
+    },
+    #[opcode(217)]
+    #[returns(u128)]
+    EmaVirtualPrice,
+    #[opcode(218)]
+    #[returns(u128)]
+    GetOracleHalfLife,
+    /// Time-weighted average spot prices (as in LastPrice) and balances of
+    /// every coin over the last `window_blocks` blocks
+    #[opcode(219)]
+    #[returns(Vec<u128>, Vec<u128>)]
+    GetTwap {
+        window_blocks: u128,
+    },
+    /// Swap fee charged on each output coin
+    #[opcode(220)]
+    #[returns(Vec<u128>)]
+    GetCoinFees,
+    #[opcode(221)]
+    #[returns(u128)]
+    GetOffpegFeeMultiplier,
+    /// Fee currently charged on swapping coin i for coin j, including the off-peg multiplier
+    #[opcode(222)]
+    #[returns(u128)]
+    GetDynamicFee {
+        i: u128,
+        j: u128,
+    },
+    /// Rate of each coin scaled by 1e18: its decimals scaling times its oracle rate
+    #[opcode(223)]
+    #[returns(Vec<u128>)]
+    GetRates,
+    /// Everything quotes read, as slope-math's PoolState encoding, so
+    /// clients can quote locally with the contract's own maths
+    #[opcode(224)]
+    #[returns(Vec<u8>)]
+    GetPoolState,
+    #[opcode(1001)]
+    #[returns(u128)]
+    GetDecimals,
+    #[opcode(1002)]
+    #[returns(u128)]
+    GetBalanceOf {
+        owner: AlkaneId,
+    },
+}
+
+impl SynthPoolMessage {
+    /// Whether the call prices the coins, and so needs their rate oracles
+    fn prices_coins(&self) -> bool {
+        use SynthPoolMessage::*;
+        matches!(
+            self,
+            AddLiquidity { .. }
+                | RemoveLiquidity { .. }
+                | RemoveLiquidityOneCoin { .. }
+                | RemoveLiquidityImbalance { .. }
+                | Swap { .. }
+                | SwapExactOut { .. }
+                | AddLiquidityWithDeadline { .. }
+                | RemoveLiquidityWithDeadline { .. }
+                | RemoveLiquidityOneCoinWithDeadline { .. }
+                | RemoveLiquidityImbalanceWithDeadline { .. }
+                | SwapWithDeadline { .. }
+                | GetVirtualPrice
+                | GetDy { .. }
+                | GetDyBreakdown { .. }
+                | GetDx { .. }
+                | GetSpotPrice { .. }
+                | GetPriceImpact { .. }
+                | CalcTokenAmount { .. }
+                | CalcTokenAmountWithFees { .. }
+                | CalcWithdrawOneCoin { .. }
+                | CalcWithdrawOneCoinWithFee { .. }
+                | GetDynamicFee { .. }
+                | GetRates
+                | GetPoolState
+        )
+    }
 }
 
 pub trait Storage {
@@ -120,6 +394,11 @@ impl Storage for AlkaneStorage {
 pub struct Logic<S: Storage> {
     storage: S,
     context: Context,
+    height: u64,
+    // rates read from the coins' rate oracles for this call, by coin index
+    oracle_rates: HashMap<usize, U256>,
+    // symbols the coins reported to InitPool, by coin index
+    coin_symbols: HashMap<usize, String>,
 }
 
 impl<S: Storage + Default> Logic<S> {
@@ -127,6 +406,9 @@ impl<S: Storage + Default> Logic<S> {
         Self {
             storage: S::default(),
             context: Context::default(),
+            height: 0,
+            oracle_rates: HashMap::new(),
+            coin_symbols: HashMap::new(),
         }
     }
     
@@ -134,15 +416,20 @@ impl<S: Storage + Default> Logic<S> {
         self.context = context;
         self
     }
+
+    pub fn with_height(mut self, height: u64) -> Self {
+        self.height = height;
+        self
+    }
 }
 
+/// The LP token is the pool alkane itself: minting emits `context.myself`
+/// to the caller and burning consumes LP that was sent in with the call.
 pub trait MintableToken {
     fn total_supply(&self) -> u128;
     fn set_total_supply(&mut self, value: u128);
-    fn balance_of(&self, owner: &AlkaneId) -> u128;
-    fn set_balance_of(&mut self, owner: &AlkaneId, value: u128);
-    fn mint(&mut self, to: &AlkaneId, amount: u128) -> Result<()>;
-    fn burn(&mut self, from: &AlkaneId, amount: u128) -> Result<()>;
+    fn mint(&mut self, amount: u128) -> Result<AlkaneTransfer>;
+    fn burn(&mut self, amount: u128) -> Result<()>;
     fn name(&self) -> String;
     fn symbol(&self) -> String;
 }
@@ -150,6 +437,8 @@ pub trait MintableToken {
 pub trait OwnedToken {
     fn owner(&self) -> AlkaneId;
     fn set_owner(&mut self, owner: AlkaneId);
+    fn future_owner(&self) -> AlkaneId;
+    fn set_future_owner(&mut self, owner: AlkaneId);
 }
 
 impl<S: Storage> MintableToken for Logic<S> {
@@ -160,35 +449,25 @@ impl<S: Storage> MintableToken for Logic<S> {
     fn set_total_supply(&mut self, value: u128) {
         self.storage.set(&b"/total_supply".to_vec(), &value.to_le_bytes().to_vec());
     }
-    fn balance_of(&self, owner: &AlkaneId) -> u128 {
-        let key = StoragePointer::wrap(&b"/balance/".to_vec()).select(&(*owner).into()).unwrap().to_vec();
-        let data = self.storage.get(&key);
-        if data.is_empty() { 0 } else { u128::from_le_bytes(data.try_into().unwrap()) }
-    }
-    fn set_balance_of(&mut self, owner: &AlkaneId, value: u128) {
-        let key = StoragePointer::wrap(&b"/balance/".to_vec()).select(&(*owner).into()).unwrap().to_vec();
-        self.storage.set(&key, &value.to_le_bytes().to_vec());
-    }
-    fn mint(&mut self, to: &AlkaneId, amount: u128) -> Result<()> {
-        let total_supply = self.total_supply();
-        self.set_total_supply(total_supply + amount);
-        let balance = self.balance_of(to);
-        self.set_balance_of(to, balance + amount);
-        Ok(())
+    fn mint(&mut self, amount: u128) -> Result<AlkaneTransfer> {
+        let total_supply = self.total_supply().try_add(amount)?;
+        self.set_total_supply(total_supply);
+        Ok(AlkaneTransfer {
+            id: self.context.myself,
+            value: amount,
+        })
     }
-    fn burn(&mut self, from: &AlkaneId, amount: u128) -> Result<()> {
-        let balance = self.balance_of(from);
-        anyhow::ensure!(balance >= amount, "Insufficient balance");
-        self.set_balance_of(from, balance - amount);
+    fn burn(&mut self, amount: u128) -> Result<()> {
         let total_supply = self.total_supply();
+        anyhow::ensure!(total_supply >= amount, SynthPoolError::InsufficientSupply { amount, total_supply });
         self.set_total_supply(total_supply - amount);
         Ok(())
     }
     fn name(&self) -> String {
-        "æfrBTC-LP".to_string()
+        String::from_utf8(self.storage.get(&b"/name".to_vec())).unwrap_or_default()
     }
     fn symbol(&self) -> String {
-        "æfrBTC-LP".to_string()
+        String::from_utf8(self.storage.get(&b"/symbol".to_vec())).unwrap_or_default()
     }
 }
 
@@ -204,9 +483,34 @@ impl<S: Storage> OwnedToken for Logic<S> {
     fn set_owner(&mut self, owner: AlkaneId) {
         self.storage.set(&b"/owner".to_vec(), &owner.into())
     }
+    fn future_owner(&self) -> AlkaneId {
+        let data = self.storage.get(&b"/future_owner".to_vec());
+        if data.is_empty() {
+            Default::default()
+        } else {
+            AlkaneId::try_from(data.as_slice().to_vec()).unwrap_or_default()
+        }
+    }
+    fn set_future_owner(&mut self, owner: AlkaneId) {
+        self.storage.set(&b"/future_owner".to_vec(), &owner.into())
+    }
 }
 
 impl<S: Storage + 'static> Logic<S> {
+    pub fn initialized(&self) -> bool {
+        let data = self.storage.get(&b"/initialized".to_vec());
+        !data.is_empty() && data[0] != 0
+    }
+    fn set_initialized(&mut self) {
+        self.storage.set(&b"/initialized".to_vec(), &vec![1u8])
+    }
+    pub fn n_coins(&self) -> usize {
+        let data = self.storage.get(&b"/n_coins".to_vec());
+        if data.is_empty() { N_COINS as usize } else { u128::from_le_bytes(data.try_into().unwrap()) as usize }
+    }
+    fn set_n_coins(&mut self, value: usize) {
+        self.storage.set(&b"/n_coins".to_vec(), &(value as u128).to_le_bytes().to_vec())
+    }
     pub fn coins(&self, index: usize) -> AlkaneId {
         let data = self.storage.get(&format!("/coins/{}", index).as_bytes().to_vec());
         if data.is_empty() {
@@ -218,16 +522,64 @@ impl<S: Storage + 'static> Logic<S> {
     fn set_coins(&mut self, index: usize, value: AlkaneId) {
         self.storage.set(&format!("/coins/{}", index).as_bytes().to_vec(), &value.into())
     }
-    pub fn A(&self) -> U256 {
-        let data = self.storage.get(&b"/A".to_vec());
+    /// Current amplification coefficient, linearly interpolated between
+    /// `initial_A` and `future_A` while a ramp is in progress.
+    pub fn A(&self) -> Result<U256> {
+        let t1 = self.future_A_height();
+        let A1 = self.future_A();
+        if self.height < t1 {
+            let A0 = self.initial_A();
+            let t0 = self.initial_A_height();
+            let elapsed = U256::from(self.height.saturating_sub(t0));
+            let duration = U256::from(t1).try_sub(U256::from(t0))?;
+            if A1 > A0 {
+                Ok(A0.try_add(A1.try_sub(A0)?.try_mul(elapsed)?.try_div(duration)?)?)
+            } else {
+                Ok(A0.try_sub(A0.try_sub(A1)?.try_mul(elapsed)?.try_div(duration)?)?)
+            }
+        } else {
+            Ok(A1)
+        }
+    }
+    fn set_A(&mut self, value: U256) {
+        self.set_initial_A(value);
+        self.set_future_A(value);
+    }
+    pub fn initial_A(&self) -> U256 {
+        let data = self.storage.get(&b"/initial_A".to_vec());
         if data.is_empty() {
             U256::ZERO
         } else {
             U256::from_le_slice(&data)
         }
     }
-    fn set_A(&mut self, value: U256) {
-        self.storage.set(&b"/A".to_vec(), &value.to_le_bytes::<32>().to_vec())
+    fn set_initial_A(&mut self, value: U256) {
+        self.storage.set(&b"/initial_A".to_vec(), &value.to_le_bytes::<32>().to_vec())
+    }
+    pub fn future_A(&self) -> U256 {
+        let data = self.storage.get(&b"/future_A".to_vec());
+        if data.is_empty() {
+            U256::ZERO
+        } else {
+            U256::from_le_slice(&data)
+        }
+    }
+    fn set_future_A(&mut self, value: U256) {
+        self.storage.set(&b"/future_A".to_vec(), &value.to_le_bytes::<32>().to_vec())
+    }
+    pub fn initial_A_height(&self) -> u64 {
+        let data = self.storage.get(&b"/initial_A_height".to_vec());
+        if data.is_empty() { 0 } else { u64::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_initial_A_height(&mut self, value: u64) {
+        self.storage.set(&b"/initial_A_height".to_vec(), &value.to_le_bytes().to_vec())
+    }
+    pub fn future_A_height(&self) -> u64 {
+        let data = self.storage.get(&b"/future_A_height".to_vec());
+        if data.is_empty() { 0 } else { u64::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_future_A_height(&mut self, value: u64) {
+        self.storage.set(&b"/future_A_height".to_vec(), &value.to_le_bytes().to_vec())
     }
     pub fn fee(&self) -> u128 {
         let data = self.storage.get(&b"/fee".to_vec());
@@ -243,6 +595,102 @@ impl<S: Storage + 'static> Logic<S> {
     fn set_admin_fee(&mut self, value: u128) {
         self.storage.set(&b"/admin_fee".to_vec(), &value.to_le_bytes().to_vec())
     }
+    pub fn future_fee(&self) -> u128 {
+        let data = self.storage.get(&b"/future_fee".to_vec());
+        if data.is_empty() { 0 } else { u128::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_future_fee(&mut self, value: u128) {
+        self.storage.set(&b"/future_fee".to_vec(), &value.to_le_bytes().to_vec())
+    }
+    pub fn future_admin_fee(&self) -> u128 {
+        let data = self.storage.get(&b"/future_admin_fee".to_vec());
+        if data.is_empty() { 0 } else { u128::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_future_admin_fee(&mut self, value: u128) {
+        self.storage.set(&b"/future_admin_fee".to_vec(), &value.to_le_bytes().to_vec())
+    }
+    /// Swap fee charged when coin `index` is the output
+    pub fn coin_fees(&self, index: usize) -> u128 {
+        let data = self.storage.get(&format!("/coin_fees/{}", index).as_bytes().to_vec());
+        if data.is_empty() { self.fee() } else { u128::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_coin_fees(&mut self, index: usize, value: u128) {
+        self.storage.set(&format!("/coin_fees/{}", index).as_bytes().to_vec(), &value.to_le_bytes().to_vec())
+    }
+    pub fn offpeg_fee_multiplier(&self) -> u128 {
+        let data = self.storage.get(&b"/offpeg_fee_multiplier".to_vec());
+        if data.is_empty() { 0 } else { u128::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_offpeg_fee_multiplier(&mut self, value: u128) {
+        self.storage.set(&b"/offpeg_fee_multiplier".to_vec(), &value.to_le_bytes().to_vec())
+    }
+    pub fn future_offpeg_fee_multiplier(&self) -> u128 {
+        let data = self.storage.get(&b"/future_offpeg_fee_multiplier".to_vec());
+        if data.is_empty() { 0 } else { u128::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_future_offpeg_fee_multiplier(&mut self, value: u128) {
+        self.storage.set(&b"/future_offpeg_fee_multiplier".to_vec(), &value.to_le_bytes().to_vec())
+    }
+    pub fn future_coin_fees(&self, index: usize) -> u128 {
+        let data = self.storage.get(&format!("/future_coin_fees/{}", index).as_bytes().to_vec());
+        if data.is_empty() { 0 } else { u128::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_future_coin_fees(&mut self, index: usize, value: u128) {
+        self.storage.set(&format!("/future_coin_fees/{}", index).as_bytes().to_vec(), &value.to_le_bytes().to_vec())
+    }
+    /// Scales coin `index` to the pool's 8 decimals, as 10**(26 - decimals)
+    fn rate_multipliers(&self, index: usize) -> U256 {
+        let data = self.storage.get(&format!("/rate_multipliers/{}", index).as_bytes().to_vec());
+        if data.is_empty() {
+            U256::from(PRECISION)
+        } else {
+            U256::from_le_slice(&data)
+        }
+    }
+    fn set_rate_multipliers(&mut self, index: usize, value: U256) {
+        self.storage.set(&format!("/rate_multipliers/{}", index).as_bytes().to_vec(), &value.to_le_bytes::<32>().to_vec())
+    }
+    /// Opcode called on coin `index` for its rate, or 0 for none
+    pub fn rate_oracles(&self, index: usize) -> u128 {
+        let data = self.storage.get(&format!("/rate_oracles/{}", index).as_bytes().to_vec());
+        if data.is_empty() { 0 } else { u128::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_rate_oracles(&mut self, index: usize, value: u128) {
+        self.storage.set(&format!("/rate_oracles/{}", index).as_bytes().to_vec(), &value.to_le_bytes().to_vec())
+    }
+    /// Block height from which the committed fee change can be applied,
+    /// or 0 when nothing is pending.
+    pub fn admin_actions_deadline(&self) -> u64 {
+        let data = self.storage.get(&b"/admin_actions_deadline".to_vec());
+        if data.is_empty() { 0 } else { u64::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_admin_actions_deadline(&mut self, value: u64) {
+        self.storage.set(&b"/admin_actions_deadline".to_vec(), &value.to_le_bytes().to_vec())
+    }
+    /// Block height from which the committed owner can accept ownership,
+    /// or 0 when no transfer is pending.
+    pub fn transfer_ownership_deadline(&self) -> u64 {
+        let data = self.storage.get(&b"/transfer_ownership_deadline".to_vec());
+        if data.is_empty() { 0 } else { u64::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_transfer_ownership_deadline(&mut self, value: u64) {
+        self.storage.set(&b"/transfer_ownership_deadline".to_vec(), &value.to_le_bytes().to_vec())
+    }
+    pub fn killed(&self) -> bool {
+        let data = self.storage.get(&b"/is_killed".to_vec());
+        !data.is_empty() && data[0] != 0
+    }
+    fn set_killed(&mut self, value: bool) {
+        self.storage.set(&b"/is_killed".to_vec(), &vec![value as u8])
+    }
+    /// Block height after which the pool can no longer be killed.
+    pub fn kill_deadline(&self) -> u64 {
+        let data = self.storage.get(&b"/kill_deadline".to_vec());
+        if data.is_empty() { 0 } else { u64::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_kill_deadline(&mut self, value: u64) {
+        self.storage.set(&b"/kill_deadline".to_vec(), &value.to_le_bytes().to_vec())
+    }
     fn balances(&self, index: usize) -> U256 {
         let data = self.storage.get(&format!("/balances/{}", index).as_bytes().to_vec());
         if data.is_empty() {
@@ -265,367 +713,1439 @@ impl<S: Storage + 'static> Logic<S> {
     fn set_admin_balances(&mut self, index: usize, value: U256) {
         self.storage.set(&format!("/admin_balances/{}", index).as_bytes().to_vec(), &value.to_le_bytes::<32>().to_vec())
     }
-
-    fn _get_balances(&self) -> [U256; 2] {
-        [self.balances(0), self.balances(1)]
+    pub fn ma_half_life(&self) -> u64 {
+        let data = self.storage.get(&b"/ma_half_life".to_vec());
+        if data.is_empty() { DEFAULT_MA_HALF_LIFE } else { u64::from_le_bytes(data.try_into().unwrap()) }
     }
-
-    fn _burn_from_context(&mut self) -> Result<U256> {
-        let context = self.context.clone();
-        let amount = context.incoming_alkanes.0.iter().find(|v| v.id == context.myself).map_or(0, |v| v.value);
-        anyhow::ensure!(amount > 0, "No LP tokens to burn in incoming transaction");
-        self.burn(&context.caller, amount)?;
-        Ok(U256::from(amount))
+    fn set_ma_half_life(&mut self, value: u64) {
+        self.storage.set(&b"/ma_half_life".to_vec(), &value.to_le_bytes().to_vec())
     }
-
-    fn _exchange(&mut self, i: usize, j: usize, dx: U256) -> Result<U256> {
-        let balances = self._get_balances();
-        let xp = balances;
-        let x = xp[i] + dx;
-        let amp = self.A();
-        let D = math::get_D(&xp, amp)?;
-        let y = math::get_y(i, j, x, &xp, amp, D)?;
-
-        let dy = xp[j] - y;
-        let fee = U256::from(self.fee());
-        let dy_fee = dy * fee / U256::from(FEE_DENOMINATOR);
-        let dy = dy - dy_fee;
-
-        let admin_fee = U256::from(self.admin_fee());
-        if admin_fee > U256::ZERO {
-            let dy_admin_fee = dy_fee * admin_fee / U256::from(FEE_DENOMINATOR);
-            println!("dy_fee: {}", dy_fee);
-            println!("admin_fee: {}", admin_fee);
-            println!("dy_admin_fee: {}", dy_admin_fee);
-            let admin_balances = self.admin_balances(j);
-            self.set_admin_balances(j, admin_balances + dy_admin_fee);
+    fn ma_last_height(&self) -> u64 {
+        let data = self.storage.get(&b"/ma_last_height".to_vec());
+        if data.is_empty() { 0 } else { u64::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_ma_last_height(&mut self, value: u64) {
+        self.storage.set(&b"/ma_last_height".to_vec(), &value.to_le_bytes().to_vec())
+    }
+    fn last_prices(&self, index: usize) -> U256 {
+        let data = self.storage.get(&format!("/last_prices/{}", index).as_bytes().to_vec());
+        if data.is_empty() {
+            U256::ZERO
+        } else {
+            U256::from_le_slice(&data)
         }
-
-        self.set_balances(i, xp[i] + dx);
-        self.set_balances(j, xp[j] - dy);
-
-        Ok(dy)
     }
-
-    fn _calc_withdraw_one_coin(&self, token_amount: U256, i: usize) -> Result<U256> {
-        let amp = self.A();
-        let xp = self._get_balances();
-        let D0 = math::get_D(&xp, amp)?;
-        let D1 = D0 - token_amount * D0 / U256::from(self.total_supply());
-        let new_y = math::get_y_D(amp, i, &xp, D1)?;
-
-        let mut xp_reduced = xp;
-        let fee = U256::from(self.fee());
-        for j in 0..N_COINS as usize {
-            let dx_expected = if j == i {
-                xp[j] * D1 / D0 - new_y
-            } else {
-                xp[j] - xp[j] * D1 / D0
-            };
-            xp_reduced[j] -= fee * dx_expected / U256::from(FEE_DENOMINATOR);
+    fn set_last_prices(&mut self, index: usize, value: U256) {
+        self.storage.set(&format!("/last_prices/{}", index).as_bytes().to_vec(), &value.to_le_bytes::<32>().to_vec())
+    }
+    fn price_emas(&self, index: usize) -> U256 {
+        let data = self.storage.get(&format!("/price_emas/{}", index).as_bytes().to_vec());
+        if data.is_empty() {
+            U256::ZERO
+        } else {
+            U256::from_le_slice(&data)
         }
-
-        let dy = xp_reduced[i] - math::get_y_D(amp, i, &xp_reduced, D1)?;
-        Ok(dy - U256::from(1))
     }
-    pub fn init_pool(
-        &mut self,
-        token_a: AlkaneId,
-        token_b: AlkaneId,
-        A: u128,
-        fee: u128,
-        admin_fee: u128,
-        owner: AlkaneId,
-    ) -> Result<CallResponse> {
-        self.set_coins(0, token_a);
-        self.set_coins(1, token_b);
-        self.set_A(U256::from(A));
-        self.set_fee(fee);
-        self.set_admin_fee(admin_fee);
-        self.set_owner(owner);
-        Ok(CallResponse::default())
+    fn set_price_emas(&mut self, index: usize, value: U256) {
+        self.storage.set(&format!("/price_emas/{}", index).as_bytes().to_vec(), &value.to_le_bytes::<32>().to_vec())
     }
-
-    pub fn add_liquidity(
-        &mut self,
-        min_mint_amount: u128,
-    ) -> Result<CallResponse> {
-        alkanes_runtime::println!("Adding liquidity with min_mint_amount: {}", min_mint_amount);
-        let mut amounts = [0u128; N_COINS as usize];
-        let coin0 = self.coins(0);
-        let coin1 = self.coins(1);
-        for transfer in self.context.incoming_alkanes.0.iter() {
-            if transfer.id == coin0 {
-                amounts[0] = transfer.value;
-            } else if transfer.id == coin1 {
-                amounts[1] = transfer.value;
-            }
+    fn last_virtual_price(&self) -> U256 {
+        let data = self.storage.get(&b"/last_virtual_price".to_vec());
+        if data.is_empty() {
+            U256::ZERO
+        } else {
+            U256::from_le_slice(&data)
         }
-        let amp = self.A();
-        let old_balances = self._get_balances();
-        let token_supply = self.total_supply();
-        let D0 = if token_supply > 0 {
-            math::get_D(&old_balances, amp)?
+    }
+    fn set_last_virtual_price(&mut self, value: U256) {
+        self.storage.set(&b"/last_virtual_price".to_vec(), &value.to_le_bytes::<32>().to_vec())
+    }
+    fn virtual_price_ema(&self) -> U256 {
+        let data = self.storage.get(&b"/virtual_price_ema".to_vec());
+        if data.is_empty() {
+            U256::ZERO
+        } else {
+            U256::from_le_slice(&data)
+        }
+    }
+    fn set_virtual_price_ema(&mut self, value: U256) {
+        self.storage.set(&b"/virtual_price_ema".to_vec(), &value.to_le_bytes::<32>().to_vec())
+    }
+    fn observation_index(&self) -> u64 {
+        let data = self.storage.get(&b"/observation_index".to_vec());
+        if data.is_empty() { 0 } else { u64::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_observation_index(&mut self, value: u64) {
+        self.storage.set(&b"/observation_index".to_vec(), &value.to_le_bytes().to_vec())
+    }
+    fn observation_count(&self) -> u64 {
+        let data = self.storage.get(&b"/observation_count".to_vec());
+        if data.is_empty() { 0 } else { u64::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_observation_count(&mut self, value: u64) {
+        self.storage.set(&b"/observation_count".to_vec(), &value.to_le_bytes().to_vec())
+    }
+    fn observation_height(&self, slot: u64) -> u64 {
+        let data = self.storage.get(&format!("/observations/{}/height", slot).as_bytes().to_vec());
+        if data.is_empty() { 0 } else { u64::from_le_bytes(data.try_into().unwrap()) }
+    }
+    fn set_observation_height(&mut self, slot: u64, value: u64) {
+        self.storage.set(&format!("/observations/{}/height", slot).as_bytes().to_vec(), &value.to_le_bytes().to_vec())
+    }
+    fn price_cumulative(&self, slot: u64, index: usize) -> U256 {
+        let data = self.storage.get(&format!("/observations/{}/price_cumulative/{}", slot, index).as_bytes().to_vec());
+        if data.is_empty() {
+            U256::ZERO
         } else {
+            U256::from_le_slice(&data)
+        }
+    }
+    fn set_price_cumulative(&mut self, slot: u64, index: usize, value: U256) {
+        self.storage.set(
+            &format!("/observations/{}/price_cumulative/{}", slot, index).as_bytes().to_vec(),
+            &value.to_le_bytes::<32>().to_vec(),
+        )
+    }
+    fn balance_cumulative(&self, slot: u64, index: usize) -> U256 {
+        let data = self.storage.get(&format!("/observations/{}/balance_cumulative/{}", slot, index).as_bytes().to_vec());
+        if data.is_empty() {
             U256::ZERO
-        };
-
-        let mut new_balances = old_balances;
-        for i in 0..N_COINS as usize {
-            new_balances[i] += U256::from(amounts[i]);
+        } else {
+            U256::from_le_slice(&data)
         }
+    }
+    fn set_balance_cumulative(&mut self, slot: u64, index: usize, value: U256) {
+        self.storage.set(
+            &format!("/observations/{}/balance_cumulative/{}", slot, index).as_bytes().to_vec(),
+            &value.to_le_bytes::<32>().to_vec(),
+        )
+    }
 
-        let D1 = math::get_D(&new_balances, amp)?;
-        anyhow::ensure!(D1 > D0, "D1 must be greater than D0");
-
-        let mint_amount;
-        if token_supply > 0 {
-            let mut fees = [U256::ZERO; 2];
-            let n_coins = U256::from(N_COINS);
-            let fee =
-                U256::from(self.fee()) * n_coins / (U256::from(4) * (n_coins - U256::from(1)));
-            let admin_fee = U256::from(self.admin_fee());
+    /// Name the LP token after the coin tickers, e.g. "æBTC/frBTC LP" and
+    /// "æBTC-frBTC-LP". A coin that reported no symbol goes by its id.
+    fn _set_lp_metadata(&mut self, coins: &[AlkaneId]) {
+        let tickers: Vec<String> = coins
+            .iter()
+            .enumerate()
+            .map(|(i, coin)| self.coin_symbols.get(&i).cloned().unwrap_or_else(|| format!("{}:{}", coin.block, coin.tx)))
+            .collect();
+        let name = format!("{} LP", tickers.join("/"));
+        let symbol = format!("{}-LP", tickers.join("-"));
+        self.storage.set(&b"/name".to_vec(), &name.into_bytes());
+        self.storage.set(&b"/symbol".to_vec(), &symbol.into_bytes());
+    }
 
-            for i in 0..N_COINS as usize {
-                let ideal_balance = D1 * old_balances[i] / D0;
-                let difference = if ideal_balance > new_balances[i] {
-                    ideal_balance - new_balances[i]
-                } else {
-                    new_balances[i] - ideal_balance
-                };
-                fees[i] = fee * difference / U256::from(FEE_DENOMINATOR);
-                let admin_balances = self.admin_balances(i);
-                self.set_admin_balances(
-                    i,
-                    admin_balances + fees[i] * admin_fee / U256::from(FEE_DENOMINATOR),
-                );
-                new_balances[i] -= fees[i];
-            }
-            let D2 = math::get_D(&new_balances, amp)?;
-            mint_amount = U256::from(token_supply) * (D2 - D0) / D0;
-        } else {
-            mint_amount = D1;
-        }
+    fn _only_owner(&self) -> Result<()> {
+        let owner = self.owner();
+        // a renounced pool has no owner; the default id must never match a caller
+        anyhow::ensure!(owner != AlkaneId::default(), SynthPoolError::NoOwner {});
+        anyhow::ensure!(self.context.caller == owner, SynthPoolError::NotOwner {});
+        Ok(())
+    }
 
+    fn _check_deadline(&self, deadline: u128) -> Result<()> {
         anyhow::ensure!(
-            mint_amount >= U256::from(min_mint_amount),
-            "!slippage"
+            deadline == 0 || self.height as u128 <= deadline,
+            SynthPoolError::DeadlinePassed { deadline, height: self.height as u128 }
         );
+        Ok(())
+    }
 
-        for i in 0..N_COINS as usize {
-            self.set_balances(i, new_balances[i]);
+    /// Per-output-coin swap fees, defaulting every coin to `fee`
+    fn _resolve_coin_fees(fee: u128, coin_fees: Vec<u128>, n_coins: usize) -> Result<Vec<u128>> {
+        if coin_fees.is_empty() {
+            return Ok(vec![fee; n_coins]);
         }
-
-        let response = CallResponse::default();
-        let context = self.context.clone();
-        self.mint(&context.caller, mint_amount.try_into().unwrap())?;
-
-        Ok(response)
+        anyhow::ensure!(
+            coin_fees.len() == n_coins,
+            SynthPoolError::WrongAmountCount { expected: n_coins as u128, actual: coin_fees.len() as u128 }
+        );
+        for &coin_fee in coin_fees.iter() {
+            anyhow::ensure!(coin_fee <= MAX_FEE, SynthPoolError::FeeTooHigh { fee: coin_fee, max: MAX_FEE });
+        }
+        Ok(coin_fees)
     }
 
-    pub fn remove_liquidity(
-        &mut self,
+    /// Rate multipliers from coin decimals, defaulting every coin to 8
+    fn _resolve_rate_multipliers(decimals: Vec<u128>, n_coins: usize) -> Result<Vec<u128>> {
+        if decimals.is_empty() {
+            return Ok(vec![LP_DECIMALS; n_coins]);
+        }
+        anyhow::ensure!(
+            decimals.len() == n_coins,
+            SynthPoolError::WrongAmountCount { expected: n_coins as u128, actual: decimals.len() as u128 }
+        );
+        for (i, &coin_decimals) in decimals.iter().enumerate() {
+            anyhow::ensure!(
+                coin_decimals <= MAX_COIN_DECIMALS,
+                SynthPoolError::DecimalsOutOfRange { index: i as u128, decimals: coin_decimals, max: MAX_COIN_DECIMALS }
+            );
+        }
+        Ok(decimals)
+    }
+
+    /// Rate oracle opcodes, defaulting every coin to none
+    fn _resolve_rate_oracles(rate_oracles: Vec<u128>, n_coins: usize) -> Result<Vec<u128>> {
+        if rate_oracles.is_empty() {
+            return Ok(vec![0; n_coins]);
+        }
+        anyhow::ensure!(
+            rate_oracles.len() == n_coins,
+            SynthPoolError::WrongAmountCount { expected: n_coins as u128, actual: rate_oracles.len() as u128 }
+        );
+        Ok(rate_oracles)
+    }
+
+    /// Rate of every coin scaled by 1e18. Coins with a rate oracle need its
+    /// rate for this call, which SynthPool reads before dispatching.
+    fn _stored_rates(&self) -> Result<Vec<U256>> {
+        (0..self.n_coins())
+            .map(|i| {
+                let multiplier = self.rate_multipliers(i);
+                if self.rate_oracles(i) == 0 {
+                    return Ok(multiplier);
+                }
+                let rate = self.oracle_rates.get(&i).ok_or_else(|| anyhow!(SynthPoolError::RateUnavailable { index: i as u128 }))?;
+                Ok(multiplier.try_mul(*rate)? / U256::from(PRECISION))
+            })
+            .collect()
+    }
+
+    /// Snapshot of everything the pool's pricing reads, which the quote and
+    /// trade paths hand to slope-math
+    fn _pool_state(&self) -> Result<PoolState> {
+        Ok(PoolState {
+            balances: self._get_balances(),
+            rates: self._stored_rates()?,
+            amp: self.A()?,
+            fee: U256::from(self.fee()),
+            coin_fees: (0..self.n_coins()).map(|i| U256::from(self.coin_fees(i))).collect(),
+            admin_fee: U256::from(self.admin_fee()),
+            offpeg_fee_multiplier: U256::from(self.offpeg_fee_multiplier()),
+            total_supply: U256::from(self.total_supply()),
+        })
+    }
+
+    fn _not_killed(&self) -> Result<()> {
+        anyhow::ensure!(!self.killed(), SynthPoolError::Killed {});
+        Ok(())
+    }
+
+    fn _get_balances(&self) -> Vec<U256> {
+        (0..self.n_coins()).map(|i| self.balances(i)).collect()
+    }
+
+    fn _event_balances(&self) -> Vec<u128> {
+        self._get_balances().iter().map(|balance| balance.saturating_to()).collect()
+    }
+
+    fn _coin_index(&self, id: &AlkaneId) -> Option<usize> {
+        (0..self.n_coins()).find(|&i| self.coins(i) == *id)
+    }
+
+    fn _incoming_lp(&self) -> Result<u128> {
+        let context = &self.context;
+        let amount = context.incoming_alkanes.0.iter().filter(|v| v.id == context.myself).map(|v| v.value).sum();
+        anyhow::ensure!(amount > 0, SynthPoolError::NoLpTokens {});
+        Ok(amount)
+    }
+
+    /// Incoming alkanes left after taking out `consumed`, merged per id, so
+    /// entry points can return everything they did not use to the caller.
+    fn _unspent_incoming(&self, consumed: &[AlkaneTransfer]) -> Result<Vec<AlkaneTransfer>> {
+        let mut remaining: Vec<AlkaneTransfer> = vec![];
+        for transfer in self.context.incoming_alkanes.0.iter() {
+            match remaining.iter_mut().find(|v| v.id == transfer.id) {
+                Some(v) => v.value = v.value.try_add(transfer.value)?,
+                None => remaining.push(transfer.clone()),
+            }
+        }
+        for transfer in consumed.iter() {
+            if let Some(v) = remaining.iter_mut().find(|v| v.id == transfer.id) {
+                v.value -= transfer.value.min(v.value);
+            }
+        }
+        remaining.retain(|v| v.value > 0);
+        Ok(remaining)
+    }
+
+    /// The single pool coin sent in for a swap, as (index, total amount).
+    fn _incoming_coin(&self) -> Result<(usize, u128)> {
+        let mut incoming: Option<(usize, u128)> = None;
+        for transfer in self.context.incoming_alkanes.0.iter() {
+            if let Some(i) = self._coin_index(&transfer.id) {
+                incoming = match incoming {
+                    None => Some((i, transfer.value)),
+                    Some((k, value)) => {
+                        anyhow::ensure!(k == i, SynthPoolError::MixedInputCoins {});
+                        Some((k, value.try_add(transfer.value)?))
+                    }
+                };
+            }
+        }
+        incoming.ok_or_else(|| anyhow!(SynthPoolError::NoInputCoin {}))
+    }
+
+    fn _burn_from_context(&mut self) -> Result<U256> {
+        let amount = self._incoming_lp()?;
+        self.burn(amount)?;
+        Ok(U256::from(amount))
+    }
+
+    fn _check_coin_index(&self, index: usize) -> Result<()> {
+        let n_coins = self.n_coins();
+        anyhow::ensure!(
+            index < n_coins,
+            SynthPoolError::CoinIndexOutOfRange { index: index as u128, n_coins: n_coins as u128 }
+        );
+        Ok(())
+    }
+
+    /// Output of swapping `dx` of coin `i` for coin `j` against the current
+    /// balances, as (dy after fees, total fee, admin share of the fee).
+    fn _get_dy(&self, i: usize, j: usize, dx: U256) -> Result<(U256, U256, U256)> {
+        Ok(self._pool_state()?.get_dy(i, j, dx)?)
+    }
+
+    /// Input of coin `i` needed to receive exactly `dy` of coin `j` after fees.
+    fn _get_dx(&self, i: usize, j: usize, dy: U256) -> Result<U256> {
+        Ok(self._pool_state()?.get_dx(i, j, dy)?)
+    }
+
+    fn _get_spot_price(&self, i: usize, j: usize) -> Result<U256> {
+        Ok(self._pool_state()?.spot_price(i, j)?)
+    }
+
+    /// Value of an EMA at the current height, given the value recorded by
+    /// the last state change and the EMA as of that change's block
+    fn _ema(&self, last: U256, ema: U256) -> Result<U256> {
+        let last_height = self.ma_last_height();
+        if self.height <= last_height {
+            return Ok(ema);
+        }
+        let alpha = math::half_life_decay(self.height - last_height, self.ma_half_life())?;
+        let precision = U256::from(PRECISION);
+        Ok(last.try_mul(precision.try_sub(alpha)?)?.try_add(ema.try_mul(alpha)?)? / precision)
+    }
+
+    /// Folds the prices left by the last state change into the EMAs, then
+    /// records the prices after this one. The EMAs only move on the first
+    /// change in a block, so a trade cannot pull the oracle in its own block.
+    fn _upkeep_oracles(&mut self) -> Result<()> {
+        let n_coins = self.n_coins();
+        if self.height > self.ma_last_height() {
+            for i in 0..n_coins {
+                let ema = self._ema(self.last_prices(i), self.price_emas(i))?;
+                self.set_price_emas(i, ema);
+            }
+            let ema = self._ema(self.last_virtual_price(), self.virtual_price_ema())?;
+            self.set_virtual_price_ema(ema);
+            let height = self.height;
+            self.set_ma_last_height(height);
+        }
+
+        let state = self._pool_state()?;
+        if state.total_supply == U256::ZERO || state.xp()?.contains(&U256::ZERO) {
+            // an emptied pool has no price; the oracle keeps the last one
+            return Ok(());
+        }
+        for (i, price) in state.prices()?.into_iter().enumerate() {
+            self.set_last_prices(i, price);
+        }
+        self.set_last_virtual_price(state.virtual_price()?);
+        Ok(())
+    }
+
+    /// Writes an observation for the current block, accumulating the prices
+    /// and balances held since the latest one. Runs before a state change,
+    /// while the balances and last prices from the previous change are
+    /// still in place.
+    fn _record_observation(&mut self) -> Result<()> {
+        let latest = self.observation_index();
+        let latest_height = self.observation_height(latest);
+        if self.height <= latest_height {
+            return Ok(());
+        }
+        let elapsed = U256::from(self.height - latest_height);
+        let slot = (latest + 1) % OBSERVATION_SLOTS;
+        for i in 0..self.n_coins() {
+            let price = self.price_cumulative(latest, i).try_add(self.last_prices(i).try_mul(elapsed)?)?;
+            let balance = self.balance_cumulative(latest, i).try_add(self.balances(i).try_mul(elapsed)?)?;
+            self.set_price_cumulative(slot, i, price);
+            self.set_balance_cumulative(slot, i, balance);
+        }
+        let height = self.height;
+        self.set_observation_height(slot, height);
+        self.set_observation_index(slot);
+        let count = self.observation_count();
+        self.set_observation_count((count + 1).min(OBSERVATION_SLOTS));
+        Ok(())
+    }
+
+    /// Ring slot of the k-th oldest observation
+    fn _observation_slot(&self, k: u64) -> u64 {
+        let oldest = self.observation_index() + OBSERVATION_SLOTS + 1 - self.observation_count();
+        (oldest + k) % OBSERVATION_SLOTS
+    }
+
+    /// Cumulative prices and balances at `target`, between the oldest
+    /// observation and the current height. Values are constant between
+    /// observations, so interpolating between them is exact.
+    fn _cumulatives_at(&self, target: u64) -> Result<(Vec<U256>, Vec<U256>)> {
+        let n_coins = self.n_coins();
+        let latest = self.observation_index();
+        let latest_height = self.observation_height(latest);
+        if target >= latest_height {
+            let elapsed = U256::from(target - latest_height);
+            let prices = (0..n_coins)
+                .map(|i| self.price_cumulative(latest, i).try_add(self.last_prices(i).try_mul(elapsed)?))
+                .collect::<Result<_, _>>()?;
+            let balances = (0..n_coins)
+                .map(|i| self.balance_cumulative(latest, i).try_add(self.balances(i).try_mul(elapsed)?))
+                .collect::<Result<_, _>>()?;
+            return Ok((prices, balances));
+        }
+
+        // newest observation at or before target; the caller keeps target
+        // at or after the oldest one
+        let (mut low, mut high) = (0, self.observation_count() - 1);
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            if self.observation_height(self._observation_slot(mid)) <= target {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        let before = self._observation_slot(low);
+        let after = self._observation_slot(low + 1);
+        let before_height = self.observation_height(before);
+        let span = U256::from(self.observation_height(after) - before_height);
+        let offset = U256::from(target - before_height);
+        let interpolate =
+            |start: U256, end: U256| start.try_add(end.try_sub(start)?.try_mul(offset)?.try_div(span)?);
+        let prices = (0..n_coins)
+            .map(|i| interpolate(self.price_cumulative(before, i), self.price_cumulative(after, i)))
+            .collect::<Result<_, _>>()?;
+        let balances = (0..n_coins)
+            .map(|i| interpolate(self.balance_cumulative(before, i), self.balance_cumulative(after, i)))
+            .collect::<Result<_, _>>()?;
+        Ok((prices, balances))
+    }
+
+    /// Moves `dx` of coin `i` in and the output of coin `j` out of the
+    /// balances, returning the same (dy, fee, admin fee) as `_get_dy`. With
+    /// `dy_out` set, pays out exactly that much instead: rounding in get_y
+    /// may yield a few units more, which stay in the pool, and the fees are
+    /// charged on the output paid.
+    fn _exchange(&mut self, i: usize, j: usize, dx: U256, dy_out: Option<U256>) -> Result<(U256, U256, U256)> {
+        self._record_observation()?;
+        let balances = self._get_balances();
+        let (mut dy, mut dy_fee, mut dy_admin_fee) = self._get_dy(i, j, dx)?;
+        if let Some(dy_out) = dy_out {
+            anyhow::ensure!(
+                dy >= dy_out,
+                SynthPoolError::OutputBelowMinimum { minimum: dy_out.saturating_to(), actual: dy.saturating_to() }
+            );
+            dy_fee = dy_fee.try_mul(dy_out)?.try_div(dy)?;
+            dy_admin_fee = dy_admin_fee.try_mul(dy_out)?.try_div(dy)?;
+            dy = dy_out;
+        }
+
+        if dy_admin_fee > U256::ZERO {
+            let admin_balances = self.admin_balances(j);
+            self.set_admin_balances(j, admin_balances.try_add(dy_admin_fee)?);
+        }
+
+        // the admin's share of the fee leaves the LPs' balance along with dy
+        self.set_balances(i, balances[i].try_add(dx)?);
+        self.set_balances(j, balances[j].try_sub(dy)?.try_sub(dy_admin_fee)?);
+        self._upkeep_oracles()?;
+
+        Ok((dy, dy_fee, dy_admin_fee))
+    }
+
+    /// LP tokens minted by depositing (or burned by withdrawing) `amounts`,
+    /// along with the per-coin imbalance fees when `with_fees` is set.
+    fn _calc_token_amount(
+        &self,
+        amounts: &[U256],
+        is_deposit: bool,
+        with_fees: bool,
+    ) -> Result<(U256, Vec<U256>)> {
+        Ok(self._pool_state()?.calc_token_amount(amounts, is_deposit, with_fees)?)
+    }
+
+    /// Coin `i` received for burning `token_amount` LP tokens, as
+    /// (dy after fees, fee charged).
+    fn _calc_withdraw_one_coin(&self, token_amount: U256, i: usize) -> Result<(U256, U256)> {
+        Ok(self._pool_state()?.calc_withdraw_one_coin(token_amount, i)?)
+    }
+
+    pub fn init_pool(
+        &mut self,
+        token_a: AlkaneId,
+        token_b: AlkaneId,
+        A: u128,
+        fee: u128,
+        admin_fee: u128,
+        owner: AlkaneId,
+    ) -> Result<CallResponse> {
+        self._init_pool(vec![token_a, token_b], A, fee, vec![], vec![], vec![], admin_fee, owner)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn init_pool_n(
+        &mut self,
+        coins: Vec<u128>,
+        A: u128,
+        fee: u128,
+        coin_fees: Vec<u128>,
+        decimals: Vec<u128>,
+        rate_oracles: Vec<u128>,
+        admin_fee: u128,
+        owner: AlkaneId,
+    ) -> Result<CallResponse> {
+        anyhow::ensure!(coins.len() % 2 == 0, SynthPoolError::MalformedCoinList { len: coins.len() as u128 });
+        let coins = coins
+            .chunks(2)
+            .map(|pair| AlkaneId::new(pair[0], pair[1]))
+            .collect();
+        self._init_pool(coins, A, fee, coin_fees, decimals, rate_oracles, admin_fee, owner)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn _init_pool(
+        &mut self,
+        coins: Vec<AlkaneId>,
+        A: u128,
+        fee: u128,
+        coin_fees: Vec<u128>,
+        decimals: Vec<u128>,
+        rate_oracles: Vec<u128>,
+        admin_fee: u128,
+        owner: AlkaneId,
+    ) -> Result<CallResponse> {
+        anyhow::ensure!(!self.initialized(), SynthPoolError::AlreadyInitialized {});
+        anyhow::ensure!(
+            coins.len() >= 2 && coins.len() <= MAX_COINS as usize,
+            SynthPoolError::InvalidCoinCount { count: coins.len() as u128, max: MAX_COINS }
+        );
+        for (i, coin) in coins.iter().enumerate() {
+            anyhow::ensure!(*coin != AlkaneId::default(), SynthPoolError::CoinNotSet { index: i as u128 });
+            anyhow::ensure!(!coins[..i].contains(coin), SynthPoolError::DuplicateCoin { index: i as u128 });
+        }
+        anyhow::ensure!(A > 0 && A < MAX_A, SynthPoolError::AOutOfRange { value: A });
+        anyhow::ensure!(fee <= MAX_FEE, SynthPoolError::FeeTooHigh { fee, max: MAX_FEE });
+        anyhow::ensure!(admin_fee <= MAX_ADMIN_FEE, SynthPoolError::AdminFeeTooHigh { admin_fee, max: MAX_ADMIN_FEE });
+        anyhow::ensure!(owner != AlkaneId::default(), SynthPoolError::OwnerNotSet {});
+        let coin_fees = Self::_resolve_coin_fees(fee, coin_fees, coins.len())?;
+        let decimals = Self::_resolve_rate_multipliers(decimals, coins.len())?;
+        let rate_oracles = Self::_resolve_rate_oracles(rate_oracles, coins.len())?;
+
+        self.set_initialized();
+        self.set_n_coins(coins.len());
+        self._set_lp_metadata(&coins);
+        for (i, coin) in coins.into_iter().enumerate() {
+            self.set_coins(i, coin);
+        }
+        self.set_A(U256::from(A));
+        self.set_fee(fee);
+        for (i, &coin_fee) in coin_fees.iter().enumerate() {
+            self.set_coin_fees(i, coin_fee);
+        }
+        for (i, &coin_decimals) in decimals.iter().enumerate() {
+            let multiplier = U256::from(10).pow(U256::from(18 + LP_DECIMALS - coin_decimals));
+            self.set_rate_multipliers(i, multiplier);
+        }
+        for (i, &opcode) in rate_oracles.iter().enumerate() {
+            self.set_rate_oracles(i, opcode);
+        }
+        self.set_admin_fee(admin_fee);
+        self.set_owner(owner);
+        let kill_deadline = self.height + KILL_DEADLINE_BLOCKS;
+        self.set_kill_deadline(kill_deadline);
+        // pegged coins start the oracle at par
+        for i in 0..self.n_coins() {
+            self.set_last_prices(i, U256::from(PRECISION));
+            self.set_price_emas(i, U256::from(PRECISION));
+        }
+        self.set_last_virtual_price(U256::from(PRECISION));
+        self.set_virtual_price_ema(U256::from(PRECISION));
+        let height = self.height;
+        self.set_ma_last_height(height);
+        self.set_observation_height(0, height);
+        self.set_observation_index(0);
+        self.set_observation_count(1);
+        let coins = (0..self.n_coins())
+            .flat_map(|i| {
+                let coin = self.coins(i);
+                [coin.block, coin.tx]
+            })
+            .collect();
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::InitPool {
+            coins,
+            a: A,
+            fee,
+            coin_fees,
+            decimals,
+            rate_oracles,
+            admin_fee,
+            owner_block: owner.block,
+            owner_tx: owner.tx,
+        }.encode();
+        Ok(response)
+    }
+
+    pub fn add_liquidity(&mut self, min_mint_amount: u128) -> Result<CallResponse> {
+        self.add_liquidity_with_deadline(min_mint_amount, 0)
+    }
+
+    pub fn add_liquidity_with_deadline(
+        &mut self,
+        min_mint_amount: u128,
+        deadline: u128,
+    ) -> Result<CallResponse> {
+        self._check_deadline(deadline)?;
+        self._not_killed()?;
+        let n_coins = self.n_coins();
+        let mut amounts = vec![0u128; n_coins];
+        for transfer in self.context.incoming_alkanes.0.iter() {
+            if let Some(i) = self._coin_index(&transfer.id) {
+                amounts[i] = amounts[i].try_add(transfer.value)?;
+            }
+        }
+        let consumed: Vec<AlkaneTransfer> = amounts
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| AlkaneTransfer { id: self.coins(i), value })
+            .collect();
+        let amounts: Vec<U256> = amounts.into_iter().map(U256::from).collect();
+        self._record_observation()?;
+        let old_balances = self._get_balances();
+        let (mint_amount, fees) = self._calc_token_amount(&amounts, true, true)?;
+
+        anyhow::ensure!(
+            mint_amount >= U256::from(min_mint_amount),
+            SynthPoolError::OutputBelowMinimum { minimum: min_mint_amount, actual: mint_amount.saturating_to() }
+        );
+
+        let admin_fee = U256::from(self.admin_fee());
+        for i in 0..n_coins {
+            let admin_share = fees[i].try_mul(admin_fee)? / U256::from(FEE_DENOMINATOR);
+            let admin_balances = self.admin_balances(i);
+            self.set_admin_balances(i, admin_balances.try_add(admin_share)?);
+            self.set_balances(i, old_balances[i].try_add(amounts[i])?.try_sub(admin_share)?);
+        }
+
+        let lp = self.mint(math::to_u128(mint_amount)?)?;
+        self._upkeep_oracles()?;
+        let event = SynthPoolEvent::AddLiquidity {
+            token_amounts: consumed.iter().map(|transfer| transfer.value).collect(),
+            fees: fees.iter().map(|fee| fee.saturating_to()).collect(),
+            minted: lp.value,
+            balances: self._event_balances(),
+            token_supply: self.total_supply(),
+        };
+        let mut outgoing_alkanes = vec![lp];
+        outgoing_alkanes.extend(self._unspent_incoming(&consumed)?);
+
+        Ok(CallResponse {
+            alkanes: AlkaneTransferParcel(outgoing_alkanes),
+            data: event.encode(),
+        })
+    }
+
+    pub fn remove_liquidity(&mut self, min_amounts: Vec<u128>) -> Result<CallResponse> {
+        self.remove_liquidity_with_deadline(min_amounts, 0)
+    }
+
+    pub fn remove_liquidity_with_deadline(
+        &mut self,
         min_amounts: Vec<u128>,
+        deadline: u128,
     ) -> Result<CallResponse> {
+        self._check_deadline(deadline)?;
+        let n_coins = self.n_coins();
+        anyhow::ensure!(
+            min_amounts.len() == n_coins,
+            SynthPoolError::WrongAmountCount { expected: n_coins as u128, actual: min_amounts.len() as u128 }
+        );
         let total_supply = self.total_supply();
-        let mut amounts = [U256::ZERO; 2];
+        let mut amounts = vec![U256::ZERO; n_coins];
+        self._record_observation()?;
         let balances = self._get_balances();
         let amount_u256 = self._burn_from_context()?;
 
-        for i in 0..N_COINS as usize {
-            let value = balances[i] * amount_u256 / U256::from(total_supply);
+        for i in 0..n_coins {
+            let value = balances[i].try_mul(amount_u256)?.try_div(U256::from(total_supply))?;
             anyhow::ensure!(
                 value >= U256::from(min_amounts[i]),
-                "Withdrawal resulted in fewer coins than expected"
+                SynthPoolError::OutputBelowMinimum { minimum: min_amounts[i], actual: value.saturating_to() }
             );
             amounts[i] = value;
-            self.set_balances(i, balances[i] - value);
+            self.set_balances(i, balances[i].try_sub(value)?);
+        }
+        // the balanced exit needs no prices, so a missing rate leaves the
+        // oracles untouched rather than half updated
+        if self._stored_rates().is_ok() {
+            self._upkeep_oracles()?;
         }
 
         let mut outgoing_alkanes = vec![];
-        for i in 0..N_COINS as usize {
+        for i in 0..n_coins {
             outgoing_alkanes.push(AlkaneTransfer {
                 id: self.coins(i),
-                value: amounts[i].try_into().unwrap(),
+                value: math::to_u128(amounts[i])?,
             });
         }
+        outgoing_alkanes.extend(self._unspent_incoming(&[AlkaneTransfer {
+            id: self.context.myself,
+            value: math::to_u128(amount_u256)?,
+        }])?);
+        let event = SynthPoolEvent::RemoveLiquidity {
+            token_amounts: amounts.iter().map(|amount| amount.saturating_to()).collect(),
+            burned: amount_u256.saturating_to(),
+            balances: self._event_balances(),
+            token_supply: self.total_supply(),
+        };
 
         Ok(CallResponse {
             alkanes: AlkaneTransferParcel(outgoing_alkanes),
-            ..Default::default()
+            data: event.encode(),
         })
     }
 
-    pub fn remove_liquidity_imbalance(
+    pub fn remove_liquidity_imbalance(&mut self, amounts: Vec<u128>, max_burn_amount: u128) -> Result<CallResponse> {
+        self.remove_liquidity_imbalance_with_deadline(amounts, max_burn_amount, 0)
+    }
+
+    pub fn remove_liquidity_imbalance_with_deadline(
         &mut self,
         amounts: Vec<u128>,
         max_burn_amount: u128,
+        deadline: u128,
     ) -> Result<CallResponse> {
-        let amp = self.A();
+        self._check_deadline(deadline)?;
+        self._not_killed()?;
+        let n_coins = self.n_coins();
+        anyhow::ensure!(
+            amounts.len() == n_coins,
+            SynthPoolError::WrongAmountCount { expected: n_coins as u128, actual: amounts.len() as u128 }
+        );
+        let incoming_lp = self._incoming_lp()?;
+        self._record_observation()?;
         let old_balances = self._get_balances();
-        let token_supply = self.total_supply();
-        let D0 = math::get_D(&old_balances, amp)?;
-
-        let mut new_balances = old_balances;
-        for i in 0..N_COINS as usize {
-            new_balances[i] -= U256::from(amounts[i]);
-        }
+        let amounts_u256: Vec<U256> = amounts.iter().map(|&a| U256::from(a)).collect();
+        let (token_amount, fees) = self._calc_token_amount(&amounts_u256, false, true)?;
+        anyhow::ensure!(
+            token_amount <= U256::from(max_burn_amount),
+            SynthPoolError::InputAboveMaximum { maximum: max_burn_amount, actual: token_amount.saturating_to() }
+        );
 
-        let D1 = math::get_D(&new_balances, amp)?;
-        let mut fees = [U256::ZERO; 2];
-        let n_coins = U256::from(N_COINS);
-        let fee =
-            U256::from(self.fee()) * n_coins / (U256::from(4) * (n_coins - U256::from(1)));
         let admin_fee = U256::from(self.admin_fee());
-
-        for i in 0..N_COINS as usize {
-            let ideal_balance = D1 * old_balances[i] / D0;
-            let difference = if ideal_balance > new_balances[i] {
-                ideal_balance - new_balances[i]
-            } else {
-                new_balances[i] - ideal_balance
-            };
-            fees[i] = fee * difference / U256::from(FEE_DENOMINATOR);
+        for i in 0..n_coins {
+            let admin_share = fees[i].try_mul(admin_fee)? / U256::from(FEE_DENOMINATOR);
             let admin_balance = self.admin_balances(i);
-            self.set_admin_balances(
-                i,
-                admin_balance + fees[i] * admin_fee / U256::from(FEE_DENOMINATOR),
-            );
-            new_balances[i] -= fees[i];
+            self.set_admin_balances(i, admin_balance.try_add(admin_share)?);
+            self.set_balances(i, old_balances[i].try_sub(amounts_u256[i])?.try_sub(admin_share)?);
         }
 
-        let D2 = math::get_D(&new_balances, amp)?;
-        let token_amount = U256::from(token_supply) * (D0 - D2) / D0;
+        let token_amount = math::to_u128(token_amount)?;
         anyhow::ensure!(
-            token_amount <= U256::from(max_burn_amount),
-            "!slippage"
+            token_amount <= incoming_lp,
+            SynthPoolError::InsufficientLpTokens { required: token_amount, provided: incoming_lp }
         );
-
-        for i in 0..N_COINS as usize {
-            self.set_balances(i, old_balances[i] - U256::from(amounts[i]));
-        }
-
-        let caller = self.context.caller.clone();
-        self.burn(&caller, token_amount.try_into().unwrap())?;
+        self.burn(token_amount)?;
+        self._upkeep_oracles()?;
         let mut outgoing_alkanes = vec![];
-        for i in 0..N_COINS as usize {
+        for i in 0..n_coins {
             outgoing_alkanes.push(AlkaneTransfer {
                 id: self.coins(i),
-                value: amounts[i].try_into().unwrap(),
+                value: amounts[i],
             });
         }
+        // LP sent beyond what the withdrawal burns is refunded here
+        outgoing_alkanes.extend(self._unspent_incoming(&[AlkaneTransfer {
+            id: self.context.myself,
+            value: token_amount,
+        }])?);
+        let event = SynthPoolEvent::RemoveLiquidityImbalance {
+            token_amounts: amounts,
+            fees: fees.iter().map(|fee| fee.saturating_to()).collect(),
+            burned: token_amount,
+            balances: self._event_balances(),
+            token_supply: self.total_supply(),
+        };
 
         Ok(CallResponse {
             alkanes: AlkaneTransferParcel(outgoing_alkanes),
-            ..Default::default()
+            data: event.encode(),
         })
     }
 
-    pub fn remove_liquidity_one_coin(
+    pub fn remove_liquidity_one_coin(&mut self, i: u128, min_amount: u128) -> Result<CallResponse> {
+        self.remove_liquidity_one_coin_with_deadline(i, min_amount, 0)
+    }
+
+    pub fn remove_liquidity_one_coin_with_deadline(
         &mut self,
         i: u128,
         min_amount: u128,
+        deadline: u128,
     ) -> Result<CallResponse> {
+        self._check_deadline(deadline)?;
+        self._not_killed()?;
         let i_usize = i as usize;
-        let token_amount_u256 = self._burn_from_context()?;
+        self._check_coin_index(i_usize)?;
+        let token_amount_u256 = U256::from(self._incoming_lp()?);
         let min_amount_u256 = U256::from(min_amount);
 
-        let dy = self._calc_withdraw_one_coin(token_amount_u256, i_usize)?;
-        anyhow::ensure!(dy >= min_amount_u256, "Not enough coins removed");
-
-        let balance = self.balances(i_usize);
-        self.set_balances(i_usize, balance - dy);
+        // price the withdrawal against the supply before burning
+        let (dy, dy_fee) = self._calc_withdraw_one_coin(token_amount_u256, i_usize)?;
+        anyhow::ensure!(
+            dy >= min_amount_u256,
+            SynthPoolError::OutputBelowMinimum { minimum: min_amount, actual: dy.saturating_to() }
+        );
+        let burned = self._burn_from_context()?;
+        self._record_observation()?;
+
+        // as in swaps, the admin's share of the fee leaves the LPs' balance
+        let dy_admin_fee = dy_fee.try_mul(U256::from(self.admin_fee()))? / U256::from(FEE_DENOMINATOR);
+        let admin_balance = self.admin_balances(i_usize);
+        self.set_admin_balances(i_usize, admin_balance.try_add(dy_admin_fee)?);
+        let balance = self.balances(i_usize).try_sub(dy)?.try_sub(dy_admin_fee)?;
+        self.set_balances(i_usize, balance);
+        self._upkeep_oracles()?;
+
+        let mut outgoing_alkanes = vec![AlkaneTransfer {
+            id: self.coins(i_usize),
+            value: math::to_u128(dy)?,
+        }];
+        outgoing_alkanes.extend(self._unspent_incoming(&[AlkaneTransfer {
+            id: self.context.myself,
+            value: math::to_u128(burned)?,
+        }])?);
+        let event = SynthPoolEvent::RemoveLiquidityOne {
+            coin_index: i,
+            coin_amount: dy.saturating_to(),
+            fee: dy_fee.saturating_to(),
+            burned: burned.saturating_to(),
+            balances: self._event_balances(),
+            token_supply: self.total_supply(),
+        };
 
         Ok(CallResponse {
-            alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
-                id: self.coins(i_usize),
-                value: dy.try_into().unwrap(),
-            }]),
-            ..Default::default()
+            alkanes: AlkaneTransferParcel(outgoing_alkanes),
+            data: event.encode(),
         })
     }
 
-    pub fn swap(
+    pub fn swap(&mut self, j: u128, min_dy: u128) -> Result<CallResponse> {
+        self.swap_with_deadline(j, min_dy, 0)
+    }
+
+    pub fn swap_with_deadline(
         &mut self,
         j: u128,
         min_dy: u128,
+        deadline: u128,
     ) -> Result<CallResponse> {
+        self._check_deadline(deadline)?;
+        self._not_killed()?;
         let j_usize = j as usize;
-        let coin0 = self.coins(0);
-        let coin1 = self.coins(1);
-        let mut incoming_transfer = None;
-        for transfer in self.context.incoming_alkanes.0.iter() {
-            if transfer.id == coin0 || transfer.id == coin1 {
-                anyhow::ensure!(incoming_transfer.is_none(), "Cannot swap more than one coin at a time");
-                incoming_transfer = Some(transfer);
-            }
-        }
-        let transfer = incoming_transfer.ok_or_else(|| anyhow!("No coin to swap provided in transaction"))?;
-        let i = if transfer.id == coin0 { 0 } else { 1 };
-        anyhow::ensure!(i != j_usize, "Cannot swap a coin for itself");
+        self._check_coin_index(j_usize)?;
+        let (i, dx) = self._incoming_coin()?;
 
-        let dx_u256 = U256::from(transfer.value);
+        let dx_u256 = U256::from(dx);
         let min_dy_u256 = U256::from(min_dy);
 
-        let dy = self._exchange(i, j_usize, dx_u256)?;
-        anyhow::ensure!(dy >= min_dy_u256, "Slippage screwed you");
+        let (dy, dy_fee, dy_admin_fee) = self._exchange(i, j_usize, dx_u256, None)?;
+        anyhow::ensure!(
+            dy >= min_dy_u256,
+            SynthPoolError::OutputBelowMinimum { minimum: min_dy, actual: dy.saturating_to() }
+        );
+
+        let mut outgoing_alkanes = vec![AlkaneTransfer {
+            id: self.coins(j_usize),
+            value: math::to_u128(dy)?,
+        }];
+        outgoing_alkanes.extend(self._unspent_incoming(&[AlkaneTransfer {
+            id: self.coins(i),
+            value: dx,
+        }])?);
+        let event = SynthPoolEvent::TokenExchange {
+            sold_id: i as u128,
+            tokens_sold: dx,
+            bought_id: j,
+            tokens_bought: dy.saturating_to(),
+            fee: dy_fee.saturating_to(),
+            admin_fee: dy_admin_fee.saturating_to(),
+            balances: self._event_balances(),
+            token_supply: self.total_supply(),
+        };
 
         Ok(CallResponse {
-            alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
-                id: self.coins(j_usize),
-                value: dy.try_into().unwrap(),
-            }]),
-            ..Default::default()
+            alkanes: AlkaneTransferParcel(outgoing_alkanes),
+            data: event.encode(),
+        })
+    }
+
+    pub fn swap_exact_out(
+        &mut self,
+        j: u128,
+        dy: u128,
+        max_dx: u128,
+        deadline: u128,
+    ) -> Result<CallResponse> {
+        self._check_deadline(deadline)?;
+        self._not_killed()?;
+        let j_usize = j as usize;
+        self._check_coin_index(j_usize)?;
+        anyhow::ensure!(dy > 0, SynthPoolError::ZeroAmount {});
+        let (i, incoming) = self._incoming_coin()?;
+
+        let dx = self._get_dx(i, j_usize, U256::from(dy))?;
+        anyhow::ensure!(
+            dx <= U256::from(max_dx),
+            SynthPoolError::InputAboveMaximum { maximum: max_dx, actual: dx.saturating_to() }
+        );
+        anyhow::ensure!(
+            dx <= U256::from(incoming),
+            SynthPoolError::InsufficientInput { required: dx.saturating_to(), provided: incoming }
+        );
+
+        let (_, dy_fee, dy_admin_fee) = self._exchange(i, j_usize, dx, Some(U256::from(dy)))?;
+
+        let mut outgoing_alkanes = vec![AlkaneTransfer {
+            id: self.coins(j_usize),
+            value: dy,
+        }];
+        outgoing_alkanes.extend(self._unspent_incoming(&[AlkaneTransfer {
+            id: self.coins(i),
+            value: math::to_u128(dx)?,
+        }])?);
+        let event = SynthPoolEvent::TokenExchange {
+            sold_id: i as u128,
+            tokens_sold: dx.saturating_to(),
+            bought_id: j,
+            tokens_bought: dy,
+            fee: dy_fee.saturating_to(),
+            admin_fee: dy_admin_fee.saturating_to(),
+            balances: self._event_balances(),
+            token_supply: self.total_supply(),
+        };
+
+        Ok(CallResponse {
+            alkanes: AlkaneTransferParcel(outgoing_alkanes),
+            data: event.encode(),
         })
     }
 
     pub fn claim_admin_fees(&mut self) -> Result<CallResponse> {
-        let owner = self.owner();
-        anyhow::ensure!(self.context.caller == owner, "Not the owner");
+        self._only_owner()?;
         let mut outgoing_alkanes = vec![];
-        for i in 0..N_COINS as usize {
-            let amount = self.admin_balances(i);
-            if amount > U256::ZERO {
+        let mut amounts = vec![];
+        for i in 0..self.n_coins() {
+            let amount = math::to_u128(self.admin_balances(i))?;
+            amounts.push(amount);
+            if amount > 0 {
                 self.set_admin_balances(i, U256::ZERO);
                 outgoing_alkanes.push(AlkaneTransfer {
                     id: self.coins(i),
-                    value: amount.try_into().unwrap(),
+                    value: amount,
                 });
             }
         }
+        outgoing_alkanes.extend(self._unspent_incoming(&[])?);
         Ok(CallResponse {
             alkanes: AlkaneTransferParcel(outgoing_alkanes),
-            ..Default::default()
+            data: SynthPoolEvent::ClaimAdminFees { amounts }.encode(),
         })
     }
 
+    pub fn ramp_a(&mut self, future_a: u128, future_height: u128) -> Result<CallResponse> {
+        self._only_owner()?;
+        let height = self.height;
+        let earliest = self.initial_A_height() + MIN_RAMP_BLOCKS;
+        anyhow::ensure!(
+            height >= earliest,
+            SynthPoolError::RampTooSoon { earliest: earliest as u128, height: height as u128 }
+        );
+        let future_height = u64::try_from(future_height)
+            .map_err(|_| anyhow!(SynthPoolError::FutureHeightOutOfRange { future_height }))?;
+        anyhow::ensure!(
+            future_height >= height + MIN_RAMP_BLOCKS,
+            SynthPoolError::RampTooShort {
+                future_height: future_height as u128,
+                earliest: (height + MIN_RAMP_BLOCKS) as u128,
+            }
+        );
+
+        let initial_a = self.A()?;
+        let future_a_u256 = U256::from(future_a);
+        anyhow::ensure!(future_a > 0 && future_a < MAX_A, SynthPoolError::FutureAOutOfRange { future_a });
+        let max_change = U256::from(MAX_A_CHANGE);
+        if future_a_u256 < initial_a {
+            anyhow::ensure!(
+                future_a_u256.try_mul(max_change)? >= initial_a,
+                SynthPoolError::ADecreaseTooLarge { initial_a: initial_a.saturating_to(), future_a }
+            );
+        } else {
+            anyhow::ensure!(
+                future_a_u256 <= initial_a.try_mul(max_change)?,
+                SynthPoolError::AIncreaseTooLarge { initial_a: initial_a.saturating_to(), future_a }
+            );
+        }
+
+        self.set_initial_A(initial_a);
+        self.set_future_A(future_a_u256);
+        self.set_initial_A_height(height);
+        self.set_future_A_height(future_height);
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::RampA {
+            old_a: initial_a.saturating_to(),
+            new_a: future_a,
+            initial_height: height as u128,
+            future_height: future_height as u128,
+        }.encode();
+        Ok(response)
+    }
+
+    pub fn stop_ramp_a(&mut self) -> Result<CallResponse> {
+        self._only_owner()?;
+        let current_a = self.A()?;
+        let height = self.height;
+        self.set_initial_A(current_a);
+        self.set_future_A(current_a);
+        self.set_initial_A_height(height);
+        self.set_future_A_height(height);
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::StopRampA {
+            a: current_a.saturating_to(),
+            height: height as u128,
+        }.encode();
+        Ok(response)
+    }
+
+    pub fn commit_new_fee(
+        &mut self,
+        fee: u128,
+        admin_fee: u128,
+        coin_fees: Vec<u128>,
+        offpeg_fee_multiplier: u128,
+    ) -> Result<CallResponse> {
+        self._only_owner()?;
+        let deadline = self.admin_actions_deadline();
+        anyhow::ensure!(deadline == 0, SynthPoolError::ActiveAction { deadline: deadline as u128 });
+        anyhow::ensure!(fee <= MAX_FEE, SynthPoolError::FeeTooHigh { fee, max: MAX_FEE });
+        anyhow::ensure!(admin_fee <= MAX_ADMIN_FEE, SynthPoolError::AdminFeeTooHigh { admin_fee, max: MAX_ADMIN_FEE });
+        let coin_fees = Self::_resolve_coin_fees(fee, coin_fees, self.n_coins())?;
+        // the scaled fee must stay within MAX_FEE on every coin
+        let highest_fee = coin_fees.iter().copied().fold(fee, u128::max);
+        let max_multiplier = (MAX_FEE * FEE_DENOMINATOR).checked_div(highest_fee).unwrap_or(u128::MAX);
+        anyhow::ensure!(
+            offpeg_fee_multiplier <= max_multiplier,
+            SynthPoolError::OffpegFeeMultiplierTooHigh { offpeg_fee_multiplier, max: max_multiplier }
+        );
+        let deadline = self.height + ADMIN_ACTIONS_DELAY;
+        self.set_admin_actions_deadline(deadline);
+        self.set_future_fee(fee);
+        self.set_future_admin_fee(admin_fee);
+        for (i, &coin_fee) in coin_fees.iter().enumerate() {
+            self.set_future_coin_fees(i, coin_fee);
+        }
+        self.set_future_offpeg_fee_multiplier(offpeg_fee_multiplier);
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::CommitNewFee {
+            deadline: deadline as u128,
+            fee,
+            admin_fee,
+            coin_fees,
+            offpeg_fee_multiplier,
+        }.encode();
+        Ok(response)
+    }
+
+    pub fn apply_new_fee(&mut self) -> Result<CallResponse> {
+        self._only_owner()?;
+        let deadline = self.admin_actions_deadline();
+        anyhow::ensure!(deadline != 0, SynthPoolError::NoActiveAction {});
+        anyhow::ensure!(
+            self.height >= deadline,
+            SynthPoolError::ActionDelayNotPassed { deadline: deadline as u128, height: self.height as u128 }
+        );
+        self.set_admin_actions_deadline(0);
+        let fee = self.future_fee();
+        let admin_fee = self.future_admin_fee();
+        self.set_fee(fee);
+        self.set_admin_fee(admin_fee);
+        let mut coin_fees = vec![];
+        for i in 0..self.n_coins() {
+            let coin_fee = self.future_coin_fees(i);
+            self.set_coin_fees(i, coin_fee);
+            coin_fees.push(coin_fee);
+        }
+        let offpeg_fee_multiplier = self.future_offpeg_fee_multiplier();
+        self.set_offpeg_fee_multiplier(offpeg_fee_multiplier);
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::NewFee {
+            fee,
+            admin_fee,
+            coin_fees,
+            offpeg_fee_multiplier,
+        }.encode();
+        Ok(response)
+    }
+
+    pub fn revert_new_parameters(&mut self) -> Result<CallResponse> {
+        self._only_owner()?;
+        self.set_admin_actions_deadline(0);
+        // clear the proposal so GetPendingParameters no longer shows it
+        self.set_future_fee(0);
+        self.set_future_admin_fee(0);
+        self.set_future_offpeg_fee_multiplier(0);
+        for i in 0..self.n_coins() {
+            self.set_future_coin_fees(i, 0);
+        }
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::RevertNewParameters {}.encode();
+        Ok(response)
+    }
+
+    pub fn commit_transfer_ownership(&mut self, new_owner: AlkaneId) -> Result<CallResponse> {
+        self._only_owner()?;
+        let deadline = self.transfer_ownership_deadline();
+        anyhow::ensure!(deadline == 0, SynthPoolError::ActiveTransfer { deadline: deadline as u128 });
+        anyhow::ensure!(new_owner != AlkaneId::default(), SynthPoolError::NewOwnerNotSet {});
+        let deadline = self.height + ADMIN_ACTIONS_DELAY;
+        self.set_transfer_ownership_deadline(deadline);
+        self.set_future_owner(new_owner);
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::CommitTransferOwnership {
+            deadline: deadline as u128,
+            new_owner_block: new_owner.block,
+            new_owner_tx: new_owner.tx,
+        }.encode();
+        Ok(response)
+    }
+
+    pub fn apply_transfer_ownership(&mut self) -> Result<CallResponse> {
+        let deadline = self.transfer_ownership_deadline();
+        anyhow::ensure!(deadline != 0, SynthPoolError::NoActiveTransfer {});
+        let future_owner = self.future_owner();
+        anyhow::ensure!(self.context.caller == future_owner, SynthPoolError::NotPendingOwner {});
+        anyhow::ensure!(
+            self.height >= deadline,
+            SynthPoolError::TransferDelayNotPassed { deadline: deadline as u128, height: self.height as u128 }
+        );
+        self.set_transfer_ownership_deadline(0);
+        self.set_future_owner(AlkaneId::default());
+        self.set_owner(future_owner);
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::TransferOwnership {
+            owner_block: future_owner.block,
+            owner_tx: future_owner.tx,
+        }.encode();
+        Ok(response)
+    }
+
+    pub fn revert_transfer_ownership(&mut self) -> Result<CallResponse> {
+        self._only_owner()?;
+        self.set_transfer_ownership_deadline(0);
+        self.set_future_owner(AlkaneId::default());
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::RevertTransferOwnership {}.encode();
+        Ok(response)
+    }
+
+    pub fn renounce_ownership(&mut self) -> Result<CallResponse> {
+        self._only_owner()?;
+        self.set_transfer_ownership_deadline(0);
+        self.set_future_owner(AlkaneId::default());
+        self.set_owner(AlkaneId::default());
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::RenounceOwnership {}.encode();
+        Ok(response)
+    }
+
+    pub fn kill_me(&mut self) -> Result<CallResponse> {
+        self._only_owner()?;
+        let deadline = self.kill_deadline();
+        anyhow::ensure!(
+            self.height < deadline,
+            SynthPoolError::KillDeadlinePassed { deadline: deadline as u128, height: self.height as u128 }
+        );
+        self.set_killed(true);
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::Kill {}.encode();
+        Ok(response)
+    }
+
+    pub fn unkill_me(&mut self) -> Result<CallResponse> {
+        self._only_owner()?;
+        self.set_killed(false);
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::Unkill {}.encode();
+        Ok(response)
+    }
+
+    pub fn set_oracle_half_life(&mut self, half_life: u128) -> Result<CallResponse> {
+        self._only_owner()?;
+        anyhow::ensure!(
+            half_life > 0 && half_life <= MAX_MA_HALF_LIFE as u128,
+            SynthPoolError::OracleHalfLifeOutOfRange { half_life, max: MAX_MA_HALF_LIFE as u128 }
+        );
+        // settle the EMAs under the old half-life before switching
+        let n_coins = self.n_coins();
+        for i in 0..n_coins {
+            let ema = self._ema(self.last_prices(i), self.price_emas(i))?;
+            self.set_price_emas(i, ema);
+        }
+        let ema = self._ema(self.last_virtual_price(), self.virtual_price_ema())?;
+        self.set_virtual_price_ema(ema);
+        let height = self.height;
+        self.set_ma_last_height(height);
+        self.set_ma_half_life(half_life as u64);
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = SynthPoolEvent::SetOracleHalfLife { half_life }.encode();
+        Ok(response)
+    }
+
+    pub fn get_name(&self) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = self.name().into_bytes();
+        Ok(response)
+    }
+
+    pub fn get_symbol(&self) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = self.symbol().into_bytes();
+        Ok(response)
+    }
+
+    pub fn get_total_supply(&self) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = self.total_supply().to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub fn get_decimals(&self) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = LP_DECIMALS.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
     pub fn get_virtual_price(&self) -> Result<CallResponse> {
-        let balances = self._get_balances();
-        let amp = self.A();
-        let D = math::get_D(&balances, amp)?;
-        let token_supply = self.total_supply();
-        let virtual_price = D * U256::from(PRECISION) / U256::from(token_supply);
-        let mut response = CallResponse::default();
+        let virtual_price = self._pool_state()?.virtual_price()?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
         response.data = virtual_price.to_le_bytes_vec();
         Ok(response)
     }
 
     pub fn get_balances(&self) -> Result<CallResponse> {
         let balances = self._get_balances();
-        let mut response = CallResponse::default();
-        response.data.extend_from_slice(&balances[0].to_le_bytes_vec());
-        response.data.extend_from_slice(&balances[1].to_le_bytes_vec());
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        for balance in balances.iter() {
+            response.data.extend_from_slice(&balance.to_le_bytes_vec());
+        }
         Ok(response)
     }
 
     pub fn get_a(&self) -> Result<CallResponse> {
-        let mut response = CallResponse::default();
-        response.data = self.A().to_le_bytes_vec();
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = self.A()?.to_le_bytes_vec();
+        Ok(response)
+    }
+
+    pub fn get_pending_parameters(&self) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data.extend_from_slice(&self.future_fee().to_le_bytes());
+        response.data.extend_from_slice(&self.future_admin_fee().to_le_bytes());
+        response.data.extend_from_slice(&(self.admin_actions_deadline() as u128).to_le_bytes());
+        response.data.extend_from_slice(&self.future_offpeg_fee_multiplier().to_le_bytes());
+        for i in 0..self.n_coins() {
+            response.data.extend_from_slice(&self.future_coin_fees(i).to_le_bytes());
+        }
+        Ok(response)
+    }
+
+    pub fn get_offpeg_fee_multiplier(&self) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = self.offpeg_fee_multiplier().to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub fn get_dynamic_fee(&self, i: u128, j: u128) -> Result<CallResponse> {
+        let fee = self._pool_state()?.swap_fee(i as usize, j as usize)?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = math::to_u128(fee)?.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub fn get_rates(&self) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        for rate in self._stored_rates()? {
+            response.data.extend_from_slice(&math::to_u128(rate)?.to_le_bytes());
+        }
+        Ok(response)
+    }
+
+    pub fn get_pool_state(&self) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = self._pool_state()?.encode();
+        Ok(response)
+    }
+
+    pub fn get_coin_fees(&self) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        for i in 0..self.n_coins() {
+            response.data.extend_from_slice(&self.coin_fees(i).to_le_bytes());
+        }
+        Ok(response)
+    }
+
+    pub fn get_owner(&self) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = self.owner().into();
+        Ok(response)
+    }
+
+    pub fn is_killed(&self) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = (self.killed() as u128).to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub fn get_dy(&self, i: u128, j: u128, dx: u128) -> Result<CallResponse> {
+        let (dy, _, _) = self._get_dy(i as usize, j as usize, U256::from(dx))?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = math::to_u128(dy)?.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub fn get_dy_breakdown(&self, i: u128, j: u128, dx: u128) -> Result<CallResponse> {
+        let (dy, dy_fee, dy_admin_fee) = self._get_dy(i as usize, j as usize, U256::from(dx))?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data.extend_from_slice(&math::to_u128(dy)?.to_le_bytes());
+        response.data.extend_from_slice(&math::to_u128(dy.try_add(dy_fee)?)?.to_le_bytes());
+        response.data.extend_from_slice(&math::to_u128(dy_fee.try_sub(dy_admin_fee)?)?.to_le_bytes());
+        response.data.extend_from_slice(&math::to_u128(dy_admin_fee)?.to_le_bytes());
+        Ok(response)
+    }
+
+    pub fn get_dx(&self, i: u128, j: u128, dy: u128) -> Result<CallResponse> {
+        let dx = self._get_dx(i as usize, j as usize, U256::from(dy))?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = math::to_u128(dx)?.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub fn get_spot_price(&self, i: u128, j: u128) -> Result<CallResponse> {
+        let price = self._get_spot_price(i as usize, j as usize)?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = math::to_u128(price)?.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Spot price, execution price and price impact of swapping `dx` of coin
+    /// `i` for coin `j`, all scaled by 1e18.
+    pub fn get_price_impact(&self, i: u128, j: u128, dx: u128) -> Result<CallResponse> {
+        anyhow::ensure!(dx > 0, SynthPoolError::ZeroAmount {});
+        let spot_price = self._get_spot_price(i as usize, j as usize)?;
+        let (dy, _, _) = self._get_dy(i as usize, j as usize, U256::from(dx))?;
+        let execution_price = dy.try_mul(U256::from(PRECISION))?.try_div(U256::from(dx))?;
+        let impact = spot_price.saturating_sub(execution_price).try_mul(U256::from(PRECISION))?.try_div(spot_price)?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data.extend_from_slice(&math::to_u128(spot_price)?.to_le_bytes());
+        response.data.extend_from_slice(&math::to_u128(execution_price)?.to_le_bytes());
+        response.data.extend_from_slice(&math::to_u128(impact)?.to_le_bytes());
+        Ok(response)
+    }
+
+    pub fn calc_token_amount(&self, amounts: Vec<u128>, is_deposit: u128) -> Result<CallResponse> {
+        let amounts: Vec<U256> = amounts.into_iter().map(U256::from).collect();
+        let (token_amount, _) = self._calc_token_amount(&amounts, is_deposit != 0, false)?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = math::to_u128(token_amount)?.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub fn calc_token_amount_with_fees(&self, amounts: Vec<u128>, is_deposit: u128) -> Result<CallResponse> {
+        let amounts: Vec<U256> = amounts.into_iter().map(U256::from).collect();
+        let (token_amount, fees) = self._calc_token_amount(&amounts, is_deposit != 0, true)?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data.extend_from_slice(&math::to_u128(token_amount)?.to_le_bytes());
+        for fee in fees.iter() {
+            response.data.extend_from_slice(&math::to_u128(*fee)?.to_le_bytes());
+        }
+        Ok(response)
+    }
+
+    pub fn calc_withdraw_one_coin(&self, token_amount: u128, i: u128) -> Result<CallResponse> {
+        let (dy, _) = self._calc_withdraw_one_coin(U256::from(token_amount), i as usize)?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = math::to_u128(dy)?.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub fn calc_withdraw_one_coin_with_fee(&self, token_amount: u128, i: u128) -> Result<CallResponse> {
+        let (dy, fee) = self._calc_withdraw_one_coin(U256::from(token_amount), i as usize)?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data.extend_from_slice(&math::to_u128(dy)?.to_le_bytes());
+        response.data.extend_from_slice(&math::to_u128(fee)?.to_le_bytes());
+        Ok(response)
+    }
+
+    pub fn price_oracle(&self, i: u128) -> Result<CallResponse> {
+        self._check_coin_index(i as usize)?;
+        let price = self._ema(self.last_prices(i as usize), self.price_emas(i as usize))?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = math::to_u128(price)?.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub fn last_price(&self, i: u128) -> Result<CallResponse> {
+        self._check_coin_index(i as usize)?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = math::to_u128(self.last_prices(i as usize))?.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub fn ema_virtual_price(&self) -> Result<CallResponse> {
+        let virtual_price = self._ema(self.last_virtual_price(), self.virtual_price_ema())?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = math::to_u128(virtual_price)?.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub fn get_oracle_half_life(&self) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = (self.ma_half_life() as u128).to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub fn get_twap(&self, window_blocks: u128) -> Result<CallResponse> {
+        let oldest_height = self.observation_height(self._observation_slot(0));
+        let max_window = self.height.saturating_sub(oldest_height);
+        anyhow::ensure!(
+            window_blocks > 0 && window_blocks <= max_window as u128,
+            SynthPoolError::TwapWindowUnavailable { window_blocks, max_window: max_window as u128 }
+        );
+        let window = window_blocks as u64;
+        let (prices_now, balances_now) = self._cumulatives_at(self.height)?;
+        let (prices_then, balances_then) = self._cumulatives_at(self.height - window)?;
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        for (now, then) in prices_now.iter().zip(&prices_then).chain(balances_now.iter().zip(&balances_then)) {
+            let average = now.try_sub(*then)? / U256::from(window);
+            response.data.extend_from_slice(&math::to_u128(average)?.to_le_bytes());
+        }
         Ok(response)
     }
 
     pub fn forward(&self) -> Result<CallResponse> {
-        Ok(CallResponse::default())
+        Ok(CallResponse::forward(&self.context.incoming_alkanes))
     }
 }
 
@@ -645,12 +2165,108 @@ impl std::ops::DerefMut for SynthPool {
     }
 }
 
+// Entry points that need the host (cross-contract calls, balance lookups)
+// live on SynthPool and shadow the Logic methods the dispatcher would
+// otherwise reach through Deref.
+impl SynthPool {
+    /// Reads the symbol of every coin for the LP token's metadata. A coin
+    /// whose opcode 100 fails or returns no text is left out.
+    fn _read_coin_symbols(&mut self, coins: &[AlkaneId]) {
+        for (i, coin) in coins.iter().enumerate() {
+            let cellpack = Cellpack {
+                target: *coin,
+                inputs: vec![100],
+            };
+            let symbol = self
+                .staticcall(&cellpack, &AlkaneTransferParcel::default(), self.fuel().min(COIN_VIEW_FUEL))
+                .ok()
+                .and_then(|response| String::from_utf8(response.data).ok())
+                .filter(|symbol| !symbol.is_empty());
+            if let Some(symbol) = symbol {
+                self.0.coin_symbols.insert(i, symbol);
+            }
+        }
+    }
+
+    /// Reads the rate of every coin with a rate oracle. A coin whose oracle
+    /// fails or returns zero is left out, so any call that prices it reverts.
+    fn _read_oracle_rates(&mut self) {
+        if !self.initialized() {
+            return;
+        }
+        for i in 0..self.n_coins() {
+            let opcode = self.rate_oracles(i);
+            if opcode == 0 {
+                continue;
+            }
+            let cellpack = Cellpack {
+                target: self.coins(i),
+                inputs: vec![opcode],
+            };
+            let rate = self
+                .staticcall(&cellpack, &AlkaneTransferParcel::default(), self.fuel().min(COIN_VIEW_FUEL))
+                .ok()
+                .and_then(|response| response.data.get(..16).map(|data| u128::from_le_bytes(data.try_into().unwrap())))
+                .filter(|&rate| rate > 0);
+            if let Some(rate) = rate {
+                self.0.oracle_rates.insert(i, U256::from(rate));
+            }
+        }
+    }
+
+    pub fn init_pool(
+        &mut self,
+        token_a: AlkaneId,
+        token_b: AlkaneId,
+        A: u128,
+        fee: u128,
+        admin_fee: u128,
+        owner: AlkaneId,
+    ) -> Result<CallResponse> {
+        self._read_coin_symbols(&[token_a, token_b]);
+        self.0.init_pool(token_a, token_b, A, fee, admin_fee, owner)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn init_pool_n(
+        &mut self,
+        coins: Vec<u128>,
+        A: u128,
+        fee: u128,
+        coin_fees: Vec<u128>,
+        decimals: Vec<u128>,
+        rate_oracles: Vec<u128>,
+        admin_fee: u128,
+        owner: AlkaneId,
+    ) -> Result<CallResponse> {
+        let ids: Vec<AlkaneId> = coins.chunks_exact(2).map(|pair| AlkaneId::new(pair[0], pair[1])).collect();
+        self._read_coin_symbols(&ids);
+        self.0.init_pool_n(coins, A, fee, coin_fees, decimals, rate_oracles, admin_fee, owner)
+    }
+
+    pub fn get_balance_of(&self, owner: AlkaneId) -> Result<CallResponse> {
+        let mut response = CallResponse::forward(&self.context.incoming_alkanes);
+        response.data = self.balance(&owner, &self.0.context.myself).to_le_bytes().to_vec();
+        Ok(response)
+    }
+}
+
 impl AlkaneResponder for SynthPool {
      fn context(&self) -> Result<Context> {
         Ok(self.0.context.clone())
     }
     fn set_context(&mut self, context: Context) {
+        // only calls that price the coins pay for reading their rates
+        let prices_coins = context
+            .inputs
+            .split_first()
+            .and_then(|(&opcode, inputs)| SynthPoolMessage::from_opcode(opcode, inputs.to_vec()).ok())
+            .is_some_and(|message| message.prices_coins());
         self.0.context = context;
+        self.0.height = AlkaneResponder::height(self);
+        if prices_coins {
+            self._read_oracle_rates();
+        }
     }
 }
 
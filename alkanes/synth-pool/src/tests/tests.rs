@@ -16,6 +16,16 @@ fn alkane_id(s: &str) -> AlkaneId {
     AlkaneId { block, tx: 0 }
 }
 
+fn lp_amount(logic: &Logic<MockStorage>, response: &CallResponse) -> u128 {
+    response
+        .alkanes
+        .0
+        .iter()
+        .filter(|transfer| transfer.id == logic.context.myself)
+        .map(|transfer| transfer.value)
+        .sum()
+}
+
 #[wasm_bindgen_test]
 fn test_add_liquidity() -> Result<()> {
     let token_a = alkane_id("token_a");
@@ -47,12 +57,13 @@ fn test_add_liquidity() -> Result<()> {
     };
 
     logic.context = context;
-    logic.add_liquidity(1000)?;
+    let response = logic.add_liquidity(1000)?;
 
-    let lp_balance = logic.balance_of(&liquidity_provider);
+    let lp_balance = lp_amount(&logic, &response);
     
     std::println!("   └─ LP Balance: {}", lp_balance);
     assert!(lp_balance > 0);
+    assert_eq!(logic.total_supply(), lp_balance);
     
     std::println!("✅ Add liquidity test passed");
     Ok(())
@@ -89,21 +100,29 @@ fn test_remove_liquidity_imbalance() -> Result<()> {
     };
 
     logic.context = context;
-    logic.add_liquidity(1000)?;
+    let response = logic.add_liquidity(1000)?;
 
-    let lp_balance = logic.balance_of(&liquidity_provider);
+    let lp_balance = lp_amount(&logic, &response);
     assert!(lp_balance > 0);
 
     let context = Context {
         caller: liquidity_provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer {
+                id: logic.context.myself,
+                value: lp_balance,
+            },
+        ]),
         ..Default::default()
     };
 
     logic.context = context;
-    logic.remove_liquidity_imbalance(vec![100_000, 200_000], lp_balance)?;
+    let response = logic.remove_liquidity_imbalance(vec![100_000, 200_000], lp_balance)?;
 
-    let lp_balance_after = logic.balance_of(&liquidity_provider);
+    // the LP that was not burned comes back to the caller
+    let lp_balance_after = lp_amount(&logic, &response);
     assert!(lp_balance_after < lp_balance);
+    assert_eq!(logic.total_supply(), lp_balance_after);
 
     std::println!("✅ Remove liquidity imbalance test passed");
     Ok(())
@@ -140,9 +159,9 @@ fn test_remove_liquidity_one_coin() -> Result<()> {
     };
 
     logic.context = context;
-    logic.add_liquidity(1000)?;
+    let response = logic.add_liquidity(1000)?;
 
-    let lp_balance = logic.balance_of(&liquidity_provider);
+    let lp_balance = lp_amount(&logic, &response);
     assert!(lp_balance > 0);
 
     let context = Context {
@@ -159,7 +178,7 @@ fn test_remove_liquidity_one_coin() -> Result<()> {
     logic.context = context;
     logic.remove_liquidity_one_coin(0, 0)?;
 
-    let lp_balance_after = logic.balance_of(&liquidity_provider);
+    let lp_balance_after = logic.total_supply();
     assert!(lp_balance_after < lp_balance);
 
     std::println!("✅ Remove liquidity one coin test passed");
@@ -319,9 +338,9 @@ fn test_remove_liquidity() -> Result<()> {
     };
 
     logic.context = context;
-    logic.add_liquidity(1000)?;
+    let response = logic.add_liquidity(1000)?;
 
-    let lp_balance = logic.balance_of(&liquidity_provider);
+    let lp_balance = lp_amount(&logic, &response);
     assert!(lp_balance > 0);
 
     let context = Context {
@@ -338,9 +357,1605 @@ fn test_remove_liquidity() -> Result<()> {
     logic.context = context;
     logic.remove_liquidity(vec![0, 0])?;
 
-    let lp_balance = logic.balance_of(&liquidity_provider);
-    assert_eq!(lp_balance, 0);
+    assert_eq!(logic.total_supply(), 0);
 
     std::println!("✅ Remove liquidity test passed");
     Ok(())
 }
+
+#[wasm_bindgen_test]
+fn test_three_coin_pool() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let token_c = alkane_id("token_c");
+    let owner = alkane_id("owner");
+    let liquidity_provider = alkane_id("liquidity_provider");
+    let swapper = alkane_id("swapper");
+
+    let mut logic = Logic::<MockStorage>::new();
+    logic.init_pool_n(
+        vec![token_a.block, token_a.tx, token_b.block, token_b.tx, token_c.block, token_c.tx],
+        100,
+        10,
+        vec![],
+        vec![],
+        vec![],
+        1,
+        owner,
+    )?;
+    assert_eq!(logic.n_coins(), 3);
+    assert_eq!(logic.coins(2), token_c);
+
+    let context = Context {
+        caller: liquidity_provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer {
+                id: token_a,
+                value: 1_000_000,
+            },
+            AlkaneTransfer {
+                id: token_b,
+                value: 1_000_000,
+            },
+            AlkaneTransfer {
+                id: token_c,
+                value: 1_000_000,
+            },
+        ]),
+        ..Default::default()
+    };
+
+    logic.context = context;
+    let response = logic.add_liquidity(1000)?;
+    assert!(lp_amount(&logic, &response) > 0);
+
+    let context = Context {
+        caller: swapper,
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer {
+                id: token_a,
+                value: 100_000,
+            },
+        ]),
+        ..Default::default()
+    };
+
+    logic.context = context;
+    let response = logic.swap(2, 0)?;
+    assert_eq!(response.alkanes.0[0].id, token_c);
+    assert!(response.alkanes.0[0].value > 0);
+
+    let balances = logic.get_balances()?;
+    assert_eq!(balances.data.len(), 3 * 32);
+
+    std::println!("✅ Three coin pool test passed");
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_init_pool_coin_count_bounds() -> Result<()> {
+    let owner = alkane_id("owner");
+    let mut logic = Logic::<MockStorage>::new();
+    assert!(logic.init_pool_n(vec![1, 0], 100, 10, vec![], vec![], vec![], 1, owner).is_err());
+    assert!(logic.init_pool_n(vec![1, 0, 2], 100, 10, vec![], vec![], vec![], 1, owner).is_err());
+    let too_many: Vec<u128> = (1..=9u128).flat_map(|block| [block, 0]).collect();
+    assert!(logic.init_pool_n(too_many, 100, 10, vec![], vec![], vec![], 1, owner).is_err());
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_ramp_a() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+    let stranger = alkane_id("stranger");
+
+    let mut logic = Logic::<MockStorage>::new().with_height(1_000);
+    logic.init_pool(token_a, token_b, 100, 10, 1, owner)?;
+
+    logic.context = Context {
+        caller: stranger,
+        ..Default::default()
+    };
+    assert!(logic.ramp_a(200, 2_000).is_err());
+
+    logic.context = Context {
+        caller: owner,
+        ..Default::default()
+    };
+    // too short, too large an increase
+    assert!(logic.ramp_a(200, 1_100).is_err());
+    assert!(logic.ramp_a(1_001, 2_000).is_err());
+
+    logic.ramp_a(200, 2_000)?;
+    assert_eq!(logic.A()?, U256::from(100));
+
+    // a new ramp cannot start while the previous one is fresh
+    logic.height = 1_100;
+    assert!(logic.ramp_a(300, 3_000).is_err());
+
+    logic.height = 1_500;
+    assert_eq!(logic.A()?, U256::from(150));
+    let response = logic.get_a()?;
+    assert_eq!(U256::from_le_slice(&response.data), U256::from(150));
+
+    logic.stop_ramp_a()?;
+    logic.height = 1_900;
+    assert_eq!(logic.A()?, U256::from(150));
+
+    logic.height = 2_000;
+    logic.ramp_a(75, 2_500)?;
+    logic.height = 2_250;
+    assert_eq!(logic.A()?, U256::from(113));
+    logic.height = 3_000;
+    assert_eq!(logic.A()?, U256::from(75));
+
+    std::println!("✅ Ramp A test passed");
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_commit_apply_new_fee() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+
+    let mut logic = Logic::<MockStorage>::new().with_height(1_000);
+    logic.init_pool(token_a, token_b, 100, 10, 1, owner)?;
+    logic.context = Context {
+        caller: owner,
+        ..Default::default()
+    };
+
+    assert!(logic.commit_new_fee(MAX_FEE + 1, 0, vec![], 0).is_err());
+    assert!(logic.commit_new_fee(0, MAX_ADMIN_FEE + 1, vec![], 0).is_err());
+    assert!(logic.apply_new_fee().is_err());
+
+    logic.commit_new_fee(4_000_000, FEE_DENOMINATOR / 2, vec![], 0)?;
+    assert!(logic.commit_new_fee(5_000_000, 0, vec![], 0).is_err());
+
+    let pending = logic.get_pending_parameters()?.data;
+    assert_eq!(u128::from_le_bytes(pending[0..16].try_into()?), 4_000_000);
+    assert_eq!(u128::from_le_bytes(pending[16..32].try_into()?), FEE_DENOMINATOR / 2);
+    assert_eq!(
+        u128::from_le_bytes(pending[32..48].try_into()?),
+        (1_000 + ADMIN_ACTIONS_DELAY) as u128
+    );
+
+    logic.height = 1_000 + ADMIN_ACTIONS_DELAY - 1;
+    assert!(logic.apply_new_fee().is_err());
+    logic.height = 1_000 + ADMIN_ACTIONS_DELAY;
+    logic.apply_new_fee()?;
+    assert_eq!(logic.fee(), 4_000_000);
+    assert_eq!(logic.admin_fee(), FEE_DENOMINATOR / 2);
+    assert_eq!(logic.admin_actions_deadline(), 0);
+
+    logic.commit_new_fee(1, 1, vec![], 2 * FEE_DENOMINATOR)?;
+    logic.revert_new_parameters()?;
+    // a reverted proposal is no longer pending
+    let pending = logic.get_pending_parameters()?.data;
+    assert_eq!(pending, vec![0u8; 16 * 6]);
+    logic.height += ADMIN_ACTIONS_DELAY;
+    assert!(logic.apply_new_fee().is_err());
+    assert_eq!(logic.fee(), 4_000_000);
+
+    std::println!("✅ Commit/apply new fee test passed");
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_init_pool_once() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+    let attacker = alkane_id("attacker");
+
+    let mut logic = Logic::<MockStorage>::new();
+    assert!(logic.init_pool(token_a, token_a, 100, 10, 1, owner).is_err());
+    assert!(logic.init_pool(token_a, AlkaneId::default(), 100, 10, 1, owner).is_err());
+    assert!(logic.init_pool(token_a, token_b, 100, MAX_FEE + 1, 1, owner).is_err());
+    assert!(logic.init_pool(token_a, token_b, 100, 10, FEE_DENOMINATOR + 1, owner).is_err());
+    assert!(logic.init_pool(token_a, token_b, 0, 10, 1, owner).is_err());
+    assert!(!logic.initialized());
+
+    logic.init_pool(token_a, token_b, 100, 10, 1, owner)?;
+    assert!(logic.initialized());
+
+    let err = logic
+        .init_pool(token_b, token_a, 1_000, 0, 0, attacker)
+        .unwrap_err();
+    assert_eq!(err.downcast_ref::<SynthPoolError>(), Some(&SynthPoolError::AlreadyInitialized {}));
+    assert_eq!(logic.owner(), owner);
+    assert_eq!(logic.coins(0), token_a);
+    assert_eq!(logic.A()?, U256::from(100));
+
+    std::println!("✅ Init pool once test passed");
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_transfer_ownership() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+    let multisig = alkane_id("multisig");
+    let stranger = alkane_id("stranger");
+
+    let mut logic = Logic::<MockStorage>::new().with_height(1_000);
+    logic.init_pool(token_a, token_b, 100, 10, 1, owner)?;
+
+    logic.context = Context {
+        caller: stranger,
+        ..Default::default()
+    };
+    assert!(logic.commit_transfer_ownership(stranger).is_err());
+
+    logic.context = Context {
+        caller: owner,
+        ..Default::default()
+    };
+    logic.commit_transfer_ownership(multisig)?;
+    assert!(logic.commit_transfer_ownership(stranger).is_err());
+    assert_eq!(logic.future_owner(), multisig);
+
+    logic.height = 1_000 + ADMIN_ACTIONS_DELAY;
+    // only the pending owner can accept
+    assert!(logic.apply_transfer_ownership().is_err());
+    logic.context = Context {
+        caller: multisig,
+        ..Default::default()
+    };
+    logic.apply_transfer_ownership()?;
+    assert_eq!(logic.owner(), multisig);
+    assert_eq!(logic.get_owner()?.data, Vec::<u8>::from(multisig));
+
+    logic.commit_transfer_ownership(owner)?;
+    logic.revert_transfer_ownership()?;
+    logic.height += ADMIN_ACTIONS_DELAY;
+    logic.context = Context {
+        caller: owner,
+        ..Default::default()
+    };
+    assert!(logic.apply_transfer_ownership().is_err());
+    assert_eq!(logic.owner(), multisig);
+
+    std::println!("✅ Transfer ownership test passed");
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_renounce_ownership() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+
+    let mut logic = Logic::<MockStorage>::new();
+    logic.init_pool(token_a, token_b, 100, 10, 1, owner)?;
+    logic.context = Context {
+        caller: owner,
+        ..Default::default()
+    };
+    logic.renounce_ownership()?;
+    assert_eq!(logic.owner(), AlkaneId::default());
+    assert!(logic.claim_admin_fees().is_err());
+
+    // a direct protostone call arrives with the default caller id
+    logic.context = Context::default();
+    assert!(logic.claim_admin_fees().is_err());
+    assert!(logic.commit_new_fee(1, 1, vec![], 0).is_err());
+
+    std::println!("✅ Renounce ownership test passed");
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_kill_me() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+    let liquidity_provider = alkane_id("liquidity_provider");
+
+    let mut logic = Logic::<MockStorage>::new().with_height(1_000);
+    logic.init_pool(token_a, token_b, 100, 10, 1, owner)?;
+
+    logic.context = Context {
+        caller: liquidity_provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer {
+                id: token_a,
+                value: 1_000_000,
+            },
+            AlkaneTransfer {
+                id: token_b,
+                value: 1_000_000,
+            },
+        ]),
+        ..Default::default()
+    };
+    let response = logic.add_liquidity(0)?;
+    let lp_balance = lp_amount(&logic, &response);
+
+    logic.context = Context {
+        caller: liquidity_provider,
+        ..Default::default()
+    };
+    assert!(logic.kill_me().is_err());
+
+    logic.context = Context {
+        caller: owner,
+        ..Default::default()
+    };
+    logic.kill_me()?;
+    assert_eq!(u128::from_le_bytes(logic.is_killed()?.data.try_into().unwrap()), 1);
+
+    logic.context = Context {
+        caller: liquidity_provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: token_a,
+            value: 1_000,
+        }]),
+        ..Default::default()
+    };
+    assert!(logic.swap(1, 0).is_err());
+    assert!(logic.add_liquidity(0).is_err());
+    assert!(logic.remove_liquidity_imbalance(vec![1_000, 0], lp_balance).is_err());
+
+    logic.context = Context {
+        caller: liquidity_provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: logic.context.myself,
+            value: lp_balance / 2,
+        }]),
+        ..Default::default()
+    };
+    assert!(logic.remove_liquidity_one_coin(0, 0).is_err());
+    let response = logic.remove_liquidity(vec![0, 0])?;
+    assert_eq!(response.alkanes.0.len(), 2);
+
+    logic.context = Context {
+        caller: owner,
+        ..Default::default()
+    };
+    logic.unkill_me()?;
+    assert!(!logic.killed());
+
+    // the pool can no longer be killed once the deadline has passed
+    logic.height = logic.kill_deadline();
+    assert!(logic.kill_me().is_err());
+
+    std::println!("✅ Kill me test passed");
+    Ok(())
+}
+
+fn u128_at(data: &[u8], index: usize) -> u128 {
+    u128::from_le_bytes(data[index * 16..(index + 1) * 16].try_into().unwrap())
+}
+
+fn seeded_pool(fee: u128, admin_fee: u128, amount_a: u128, amount_b: u128) -> Result<Logic<MockStorage>> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+    let liquidity_provider = alkane_id("liquidity_provider");
+
+    let mut logic = Logic::<MockStorage>::new().with_height(1_000);
+    logic.init_pool(token_a, token_b, 100 * 100, fee, admin_fee, owner)?;
+    logic.context = Context {
+        caller: liquidity_provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer {
+                id: token_a,
+                value: amount_a,
+            },
+            AlkaneTransfer {
+                id: token_b,
+                value: amount_b,
+            },
+        ]),
+        ..Default::default()
+    };
+    logic.add_liquidity(0)?;
+    Ok(logic)
+}
+
+#[wasm_bindgen_test]
+fn test_quotes() -> Result<()> {
+    let mut logic = seeded_pool(4_000_000, FEE_DENOMINATOR / 2, 1_000_000_000, 1_000_000_000)?;
+    let balances_before = logic.get_balances()?.data;
+
+    let dy = u128_at(&logic.get_dy(0, 1, 10_000_000)?.data, 0);
+    let breakdown = logic.get_dy_breakdown(0, 1, 10_000_000)?.data;
+    assert_eq!(u128_at(&breakdown, 0), dy);
+    assert_eq!(
+        u128_at(&breakdown, 1),
+        dy + u128_at(&breakdown, 2) + u128_at(&breakdown, 3)
+    );
+    assert!(u128_at(&breakdown, 3) > 0);
+    // quotes never touch storage
+    assert_eq!(logic.get_balances()?.data, balances_before);
+
+    let dx = u128_at(&logic.get_dx(0, 1, dy)?.data, 0);
+    assert!(dx >= 10_000_000 && dx <= 10_000_002);
+    assert!(u128_at(&logic.get_dy(0, 1, dx)?.data, 0) >= dy);
+
+    let spot = u128_at(&logic.get_spot_price(0, 1)?.data, 0);
+    assert!(spot.abs_diff(PRECISION) < PRECISION / 1_000_000);
+
+    let small = logic.get_price_impact(0, 1, 1_000_000)?.data;
+    let large = logic.get_price_impact(0, 1, 500_000_000)?.data;
+    assert_eq!(u128_at(&small, 0), spot);
+    assert!(u128_at(&large, 1) < u128_at(&small, 1));
+    assert!(u128_at(&large, 2) > u128_at(&small, 2));
+
+    assert!(logic.get_dy(0, 0, 1).is_err());
+    assert!(logic.get_dy(0, 2, 1).is_err());
+
+    logic.context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: alkane_id("token_a"),
+            value: 10_000_000,
+        }]),
+        ..Default::default()
+    };
+    let response = logic.swap(1, dy)?;
+    assert_eq!(response.alkanes.0[0].value, dy);
+
+    std::println!("✅ Quotes test passed");
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_spot_price_imbalanced() -> Result<()> {
+    let logic = seeded_pool(0, 0, 3_000_000_000, 1_000_000_000)?;
+    // coin 0 is abundant, so it is worth less than coin 1
+    let price_0_in_1 = u128_at(&logic.get_spot_price(0, 1)?.data, 0);
+    let price_1_in_0 = u128_at(&logic.get_spot_price(1, 0)?.data, 0);
+    assert!(price_0_in_1 < PRECISION);
+    assert!(price_1_in_0 > PRECISION);
+
+    // the marginal rate of a tiny swap matches the spot price
+    let dy = u128_at(&logic.get_dy(0, 1, 1_000_000)?.data, 0);
+    let marginal = dy * PRECISION / 1_000_000;
+    assert!(marginal.abs_diff(price_0_in_1) < PRECISION / 10_000);
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_calc_token_amount() -> Result<()> {
+    let mut logic = seeded_pool(4_000_000, FEE_DENOMINATOR / 2, 1_000_000_000, 1_000_000_000)?;
+    let liquidity_provider = alkane_id("liquidity_provider");
+
+    // balanced deposits pay no imbalance fee, so both previews agree
+    let plain = u128_at(&logic.calc_token_amount(vec![1_000_000, 1_000_000], 1)?.data, 0);
+    let with_fees = logic.calc_token_amount_with_fees(vec![1_000_000, 1_000_000], 1)?.data;
+    assert_eq!(u128_at(&with_fees, 0), plain);
+
+    let plain = u128_at(&logic.calc_token_amount(vec![50_000_000, 0], 1)?.data, 0);
+    let with_fees = logic.calc_token_amount_with_fees(vec![50_000_000, 0], 1)?.data;
+    let minted_preview = u128_at(&with_fees, 0);
+    assert!(minted_preview < plain);
+    assert!(u128_at(&with_fees, 1) > 0);
+
+    logic.context = Context {
+        caller: liquidity_provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: alkane_id("token_a"),
+            value: 50_000_000,
+        }]),
+        ..Default::default()
+    };
+    let response = logic.add_liquidity(minted_preview)?;
+    assert_eq!(lp_amount(&logic, &response), minted_preview);
+
+    let burn_preview =
+        u128_at(&logic.calc_token_amount_with_fees(vec![0, 20_000_000], 0)?.data, 0);
+    assert!(burn_preview > u128_at(&logic.calc_token_amount(vec![0, 20_000_000], 0)?.data, 0));
+    let supply_before = logic.total_supply();
+    logic.context = Context {
+        caller: liquidity_provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: logic.context.myself,
+            value: burn_preview + 1_000,
+        }]),
+        ..Default::default()
+    };
+    let response = logic.remove_liquidity_imbalance(vec![0, 20_000_000], burn_preview)?;
+    assert_eq!(supply_before - logic.total_supply(), burn_preview);
+    assert_eq!(lp_amount(&logic, &response), 1_000);
+
+    assert!(logic.calc_token_amount(vec![1, 2, 3], 1).is_err());
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_calc_withdraw_one_coin() -> Result<()> {
+    let mut logic = seeded_pool(4_000_000, 0, 1_000_000_000, 1_000_000_000)?;
+    let liquidity_provider = alkane_id("liquidity_provider");
+    let lp_balance = logic.total_supply();
+
+    let dy = u128_at(&logic.calc_withdraw_one_coin(lp_balance / 10, 1)?.data, 0);
+    let with_fee = logic.calc_withdraw_one_coin_with_fee(lp_balance / 10, 1)?.data;
+    assert_eq!(u128_at(&with_fee, 0), dy);
+    assert!(u128_at(&with_fee, 1) > 0);
+    assert!(logic.calc_withdraw_one_coin(lp_balance * 2, 1).is_err());
+    assert!(logic.calc_withdraw_one_coin(lp_balance / 10, 2).is_err());
+
+    logic.context = Context {
+        caller: liquidity_provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: logic.context.myself,
+            value: lp_balance / 10,
+        }]),
+        ..Default::default()
+    };
+    let response = logic.remove_liquidity_one_coin(1, dy)?;
+    assert_eq!(response.alkanes.0[0].value, dy);
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_lp_token_is_an_alkane() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+    let pool = alkane_id("pool");
+    let liquidity_provider = alkane_id("liquidity_provider");
+
+    let mut logic = Logic::<MockStorage>::new();
+    logic.init_pool(token_a, token_b, 100, 10, 1, owner)?;
+    logic.context = Context {
+        myself: pool,
+        caller: liquidity_provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer {
+                id: token_a,
+                value: 1_000_000,
+            },
+            AlkaneTransfer {
+                id: token_b,
+                value: 1_000_000,
+            },
+        ]),
+        ..Default::default()
+    };
+    let response = logic.add_liquidity(0)?;
+    assert_eq!(response.alkanes.0.len(), 1);
+    assert_eq!(response.alkanes.0[0].id, pool);
+    let lp = response.alkanes.0[0].value;
+
+    // imbalanced withdrawals need the LP to be sent in
+    logic.context = Context {
+        myself: pool,
+        caller: liquidity_provider,
+        ..Default::default()
+    };
+    assert!(logic.remove_liquidity_imbalance(vec![1_000, 0], lp).is_err());
+
+    // sending too little LP for the requested amounts fails
+    logic.context = Context {
+        myself: pool,
+        caller: liquidity_provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: pool,
+            value: 10,
+        }]),
+        ..Default::default()
+    };
+    assert!(logic.remove_liquidity_imbalance(vec![100_000, 0], lp).is_err());
+    assert_eq!(logic.total_supply(), lp);
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_lp_metadata() -> Result<()> {
+    let mut logic = seeded_pool(10, 1, 1_000_000, 1_000_000)?;
+    let token_a = alkane_id("token_a");
+    assert_eq!(
+        String::from_utf8(logic.get_name()?.data)?,
+        format!("{}:0/{}:0 LP", token_a.block, alkane_id("token_b").block)
+    );
+
+    assert_eq!(
+        u128::from_le_bytes(logic.get_total_supply()?.data.try_into().unwrap()),
+        logic.total_supply()
+    );
+    assert_eq!(u128::from_le_bytes(logic.get_decimals()?.data.try_into().unwrap()), 8);
+
+    let owner = alkane_id("owner");
+    let token_b = alkane_id("token_b");
+    let mut logic = Logic::<MockStorage>::new();
+    logic.coin_symbols.insert(0, "æBTC".to_string());
+    logic.coin_symbols.insert(1, "frBTC".to_string());
+    logic.init_pool(token_a, token_b, 100, 10, 1, owner)?;
+    assert_eq!(String::from_utf8(logic.get_name()?.data)?, "æBTC/frBTC LP");
+    assert_eq!(String::from_utf8(logic.get_symbol()?.data)?, "æBTC-frBTC-LP");
+
+    // token_b's symbol call failed, so it goes by its id
+    let mut logic = Logic::<MockStorage>::new();
+    logic.coin_symbols.insert(0, "æBTC".to_string());
+    logic.init_pool(token_a, token_b, 100, 10, 1, owner)?;
+    assert_eq!(String::from_utf8(logic.get_name()?.data)?, format!("æBTC/{}:0 LP", token_b.block));
+    assert_eq!(String::from_utf8(logic.get_symbol()?.data)?, format!("æBTC-{}:0-LP", token_b.block));
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_unused_alkanes_are_returned() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let stray = alkane_id("stray");
+    let mut logic = seeded_pool(10, 1, 1_000_000, 1_000_000)?;
+    let supply_before = logic.total_supply();
+
+    // duplicate transfers of a coin are deposited together, unknown ids come back
+    logic.context = Context {
+        caller: alkane_id("liquidity_provider"),
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer {
+                id: token_a,
+                value: 1_000,
+            },
+            AlkaneTransfer {
+                id: stray,
+                value: 42,
+            },
+            AlkaneTransfer {
+                id: token_a,
+                value: 1_000,
+            },
+            AlkaneTransfer {
+                id: token_b,
+                value: 2_000,
+            },
+        ]),
+        ..Default::default()
+    };
+    let response = logic.add_liquidity(0)?;
+    assert_eq!(response.alkanes.0.len(), 2);
+    assert_eq!(response.alkanes.0[1], AlkaneTransfer { id: stray, value: 42 });
+    assert_eq!(logic.balances(0), U256::from(1_002_000));
+    let lp = lp_amount(&logic, &response);
+    assert_eq!(logic.total_supply(), supply_before + lp);
+
+    logic.context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer {
+                id: stray,
+                value: 7,
+            },
+            AlkaneTransfer {
+                id: token_a,
+                value: 500,
+            },
+            AlkaneTransfer {
+                id: stray,
+                value: 8,
+            },
+            AlkaneTransfer {
+                id: token_a,
+                value: 500,
+            },
+        ]),
+        ..Default::default()
+    };
+    let dy = u128_at(&logic.get_dy(0, 1, 1_000)?.data, 0);
+    let response = logic.swap(1, 0)?;
+    // both parcels of the stray alkane come back as one
+    assert_eq!(response.alkanes.0.len(), 2);
+    assert_eq!(response.alkanes.0[0], AlkaneTransfer { id: token_b, value: dy });
+    assert_eq!(response.alkanes.0[1], AlkaneTransfer { id: stray, value: 15 });
+
+    // parcels that cannot be merged into one u128 revert instead of wrapping
+    logic.context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer { id: token_a, value: 1_000 },
+            AlkaneTransfer { id: stray, value: u128::MAX },
+            AlkaneTransfer { id: stray, value: 1 },
+        ]),
+        ..Default::default()
+    };
+    let err = logic.swap(1, 0).unwrap_err();
+    assert_eq!(err.downcast_ref::<SynthPoolError>(), Some(&SynthPoolError::MathOverflow {}));
+
+    logic.context = Context {
+        caller: alkane_id("liquidity_provider"),
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer {
+                id: logic.context.myself,
+                value: lp,
+            },
+            AlkaneTransfer {
+                id: token_b,
+                value: 3,
+            },
+        ]),
+        ..Default::default()
+    };
+    let response = logic.remove_liquidity(vec![0, 0])?;
+    assert_eq!(response.alkanes.0.len(), 3);
+    assert_eq!(response.alkanes.0[2], AlkaneTransfer { id: token_b, value: 3 });
+
+    // views and forward hand everything back untouched
+    let incoming = AlkaneTransferParcel(vec![AlkaneTransfer { id: stray, value: 5 }]);
+    logic.context = Context {
+        incoming_alkanes: incoming.clone(),
+        ..Default::default()
+    };
+    assert_eq!(logic.forward()?.alkanes.0, incoming.0);
+    assert_eq!(logic.get_virtual_price()?.alkanes.0, incoming.0);
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_swap_exact_out() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let mut logic = seeded_pool(4_000_000, FEE_DENOMINATOR / 2, 1_000_000_000, 1_000_000_000)?;
+
+    let quoted_dx = u128_at(&logic.get_dx(0, 1, 5_000_000)?.data, 0);
+    let swap_context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: token_a,
+            value: 6_000_000,
+        }]),
+        ..Default::default()
+    };
+
+    logic.context = swap_context.clone();
+    assert!(logic.swap_exact_out(1, 5_000_000, quoted_dx - 1, 0).is_err());
+    assert!(logic.swap_exact_out(1, 7_000_000, u128::MAX, 0).is_err());
+
+    let response = logic.swap_exact_out(1, 5_000_000, quoted_dx, 0)?;
+    assert_eq!(response.alkanes.0.len(), 2);
+    assert_eq!(response.alkanes.0[0], AlkaneTransfer { id: token_b, value: 5_000_000 });
+    assert_eq!(
+        response.alkanes.0[1],
+        AlkaneTransfer {
+            id: token_a,
+            value: 6_000_000 - quoted_dx,
+        }
+    );
+    assert_eq!(logic.balances(0), U256::from(1_000_000_000 + quoted_dx));
+    assert_eq!(logic.balances(1), U256::from(1_000_000_000 - 5_000_000) - logic.admin_balances(1));
+
+    // the oracles and the event see the balances left after the payout
+    let state = logic._pool_state()?;
+    for (i, price) in state.prices()?.into_iter().enumerate() {
+        assert_eq!(logic.last_prices(i), price);
+    }
+    assert_eq!(logic.last_virtual_price(), state.virtual_price()?);
+    match SynthPoolEvent::decode(&response.data) {
+        Some(SynthPoolEvent::TokenExchange { tokens_bought, admin_fee, balances, .. }) => {
+            assert_eq!(tokens_bought, 5_000_000);
+            assert_eq!(U256::from(admin_fee), logic.admin_balances(1));
+            assert_eq!(balances, logic._event_balances());
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_deadline() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let mut logic = seeded_pool(4_000_000, 0, 1_000_000_000, 1_000_000_000)?;
+    let lp_balance = logic.total_supply();
+
+    logic.context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: token_a,
+            value: 1_000_000,
+        }]),
+        ..Default::default()
+    };
+    let err = logic.swap_with_deadline(1, 0, 999).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<SynthPoolError>(),
+        Some(&SynthPoolError::DeadlinePassed { deadline: 999, height: 1000 })
+    );
+    assert!(logic.swap_exact_out(1, 500_000, u128::MAX, 999).is_err());
+    assert!(logic.add_liquidity_with_deadline(0, 999).is_err());
+    assert_eq!(logic.balances(0), U256::from(1_000_000_000u128));
+
+    // The deadline block itself is still valid, and 0 disables the check
+    logic.swap_with_deadline(1, 0, 1000)?;
+    logic.swap_with_deadline(1, 0, 0)?;
+    logic.swap(1, 0)?;
+
+    // calldata without a deadline keeps its original opcodes
+    let message = SynthPoolMessage::from_opcode(5, vec![1, 0])?;
+    assert_eq!(format!("{:?}", message), "Swap { j: 1, min_dy: 0 }");
+    let message = SynthPoolMessage::from_opcode(35, vec![1, 0, 999])?;
+    assert_eq!(format!("{:?}", message), "SwapWithDeadline { j: 1, min_dy: 0, deadline: 999 }");
+
+    logic.context = Context {
+        caller: alkane_id("provider"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: logic.context.myself,
+            value: lp_balance / 4,
+        }]),
+        ..Default::default()
+    };
+    assert!(logic.remove_liquidity_with_deadline(vec![0, 0], 999).is_err());
+    assert!(logic.remove_liquidity_one_coin_with_deadline(0, 0, 999).is_err());
+    assert!(logic.remove_liquidity_imbalance_with_deadline(vec![1_000, 1_000], u128::MAX, 999).is_err());
+    logic.remove_liquidity_with_deadline(vec![0, 0], 1000)?;
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_error_codes() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let mut logic = seeded_pool(4_000_000, 0, 1_000_000_000, 1_000_000_000)?;
+    let quoted_dy = u128_at(&logic.get_dy(0, 1, 1_000_000)?.data, 0);
+
+    logic.context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: token_a,
+            value: 1_000_000,
+        }]),
+        ..Default::default()
+    };
+    let err = logic.swap(1, quoted_dy + 1).unwrap_err();
+    let expected = SynthPoolError::OutputBelowMinimum {
+        minimum: quoted_dy + 1,
+        actual: quoted_dy,
+    };
+    assert_eq!(err.downcast_ref::<SynthPoolError>(), Some(&expected));
+    assert_eq!(expected.code(), 500);
+
+    // what the alkane wrapper reports for a failed call
+    let revert = format!("Error: {}", err);
+    assert_eq!(SynthPoolError::from_revert_data(revert.as_bytes()), Some(expected));
+
+    let err = logic.swap(5, 0).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<SynthPoolError>(),
+        Some(&SynthPoolError::CoinIndexOutOfRange { index: 5, n_coins: 2 })
+    );
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_admin_fees_leave_balances() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let provider = alkane_id("provider");
+    let mut logic = seeded_pool(4_000_000, FEE_DENOMINATOR / 2, 1_000_000_000, 1_000_000_000)?;
+    let mut holdings = [1_000_000_000u128, 1_000_000_000];
+    let paid_out = |response: &CallResponse, id: AlkaneId| -> u128 {
+        response.alkanes.0.iter().filter(|transfer| transfer.id == id).map(|transfer| transfer.value).sum()
+    };
+
+    logic.context = Context {
+        caller: provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer { id: token_a, value: 1_000_000 }]),
+        ..Default::default()
+    };
+    let response = logic.swap(1, 0)?;
+    holdings[0] += 1_000_000;
+    holdings[1] -= paid_out(&response, token_b);
+    assert!(logic.admin_balances(1) > U256::ZERO);
+
+    // imbalanced deposits and withdrawals charge fees on both coins
+    logic.context = Context {
+        caller: provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer { id: token_a, value: 100_000_000 }]),
+        ..Default::default()
+    };
+    let response = logic.add_liquidity(0)?;
+    holdings[0] += 100_000_000;
+    let lp_balance = lp_amount(&logic, &response);
+    logic.context = Context {
+        caller: provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer { id: logic.context.myself, value: lp_balance }]),
+        ..Default::default()
+    };
+    let response = logic.remove_liquidity_imbalance(vec![0, 50_000_000], lp_balance)?;
+    holdings[1] -= paid_out(&response, token_b);
+    assert!(logic.admin_balances(0) > U256::ZERO);
+
+    // one-coin withdrawals share their fee with the admin like swaps do
+    let lp_balance = lp_amount(&logic, &response);
+    logic.context = Context {
+        caller: provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer { id: logic.context.myself, value: lp_balance }]),
+        ..Default::default()
+    };
+    let admin_balance = logic.admin_balances(0);
+    let fee = u128_at(&logic.calc_withdraw_one_coin_with_fee(lp_balance, 0)?.data, 1);
+    let response = logic.remove_liquidity_one_coin(0, 0)?;
+    holdings[0] -= paid_out(&response, token_a);
+    assert!(fee > 0);
+    assert_eq!(logic.admin_balances(0), admin_balance + U256::from(fee / 2));
+
+    // the pool holds exactly what its LPs and the admin are owed
+    for i in 0..2 {
+        assert_eq!(U256::from(holdings[i]), logic.balances(i) + logic.admin_balances(i));
+    }
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_events() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let mut logic = seeded_pool(4_000_000, FEE_DENOMINATOR / 2, 1_000_000_000, 1_000_000_000)?;
+    let (dy, _, lp_fee, admin_fee) = {
+        let data = logic.get_dy_breakdown(0, 1, 1_000_000)?.data;
+        (u128_at(&data, 0), u128_at(&data, 1), u128_at(&data, 2), u128_at(&data, 3))
+    };
+
+    logic.context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: token_a,
+            value: 1_000_000,
+        }]),
+        ..Default::default()
+    };
+    let response = logic.swap(1, 0)?;
+    assert_eq!(
+        SynthPoolEvent::decode(&response.data),
+        Some(SynthPoolEvent::TokenExchange {
+            sold_id: 0,
+            tokens_sold: 1_000_000,
+            bought_id: 1,
+            tokens_bought: dy,
+            fee: lp_fee + admin_fee,
+            admin_fee,
+            balances: vec![1_001_000_000, 1_000_000_000 - dy - admin_fee],
+            token_supply: logic.total_supply(),
+        })
+    );
+
+    let supply = logic.total_supply();
+    logic.context = Context {
+        caller: alkane_id("provider"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: logic.context.myself,
+            value: supply / 10,
+        }]),
+        ..Default::default()
+    };
+    let response = logic.remove_liquidity(vec![0, 0])?;
+    match SynthPoolEvent::decode(&response.data) {
+        Some(SynthPoolEvent::RemoveLiquidity { token_amounts, burned, balances, token_supply }) => {
+            assert_eq!(token_amounts, response.alkanes.0[..2].iter().map(|t| t.value).collect::<Vec<_>>());
+            assert_eq!(burned, supply / 10);
+            assert_eq!(balances, vec![logic.balances(0).to::<u128>(), logic.balances(1).to::<u128>()]);
+            assert_eq!(token_supply, supply - supply / 10);
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    logic.context = Context {
+        caller: alkane_id("owner"),
+        ..Default::default()
+    };
+    let response = logic.commit_new_fee(1_000_000, 0, vec![], 0)?;
+    assert_eq!(
+        SynthPoolEvent::decode(&response.data),
+        Some(SynthPoolEvent::CommitNewFee {
+            deadline: 1000 + ADMIN_ACTIONS_DELAY as u128,
+            fee: 1_000_000,
+            admin_fee: 0,
+            coin_fees: vec![1_000_000, 1_000_000],
+            offpeg_fee_multiplier: 0,
+        })
+    );
+    let response = logic.kill_me()?;
+    assert_eq!(SynthPoolEvent::decode(&response.data), Some(SynthPoolEvent::Kill {}));
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_price_oracle() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let mut logic = seeded_pool(4_000_000, 0, 1_000_000_000, 1_000_000_000)?;
+    let half_life = logic.ma_half_life() as u128;
+    assert_eq!(u128_at(&logic.price_oracle(1)?.data, 0), PRECISION);
+    assert_eq!(u128_at(&logic.ema_virtual_price()?.data, 0), PRECISION);
+
+    // selling coin 0 makes coin 1 dearer
+    logic.context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: token_a,
+            value: 500_000_000,
+        }]),
+        ..Default::default()
+    };
+    logic.swap(1, 0)?;
+    let last_price = u128_at(&logic.last_price(1)?.data, 0);
+    assert!(last_price > PRECISION);
+    assert_eq!(last_price, u128_at(&logic.get_spot_price(1, 0)?.data, 0));
+
+    // the swap's own block does not move the oracle
+    assert_eq!(u128_at(&logic.price_oracle(1)?.data, 0), PRECISION);
+
+    logic.height += half_life as u64;
+    let halfway = (last_price + PRECISION) / 2;
+    assert!(u128_at(&logic.price_oracle(1)?.data, 0).abs_diff(halfway) <= 1);
+    logic.height += half_life as u64;
+    let three_quarters = (3 * last_price + PRECISION) / 4;
+    assert!(u128_at(&logic.price_oracle(1)?.data, 0).abs_diff(three_quarters) <= 1);
+    assert!(u128_at(&logic.ema_virtual_price()?.data, 0) > PRECISION);
+    assert_eq!(u128_at(&logic.price_oracle(0)?.data, 0), PRECISION);
+    assert!(logic.price_oracle(2).is_err());
+
+    // a later trade folds the elapsed blocks into the EMA and records a new last price
+    logic.swap(1, 0)?;
+    assert!(u128_at(&logic.price_oracle(1)?.data, 0).abs_diff(three_quarters) <= 1);
+    assert!(u128_at(&logic.last_price(1)?.data, 0) > last_price);
+
+    assert_eq!(math::half_life_decay(0, 6)?, U256::from(PRECISION));
+    assert_eq!(math::half_life_decay(12, 6)?, U256::from(PRECISION / 4));
+    let sqrt_half = U256::from(707_106_781_186_547_524u128);
+    assert!(math::half_life_decay(3, 6)?.abs_diff(sqrt_half) < U256::from(1_000));
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_set_oracle_half_life() -> Result<()> {
+    let mut logic = seeded_pool(4_000_000, 0, 1_000_000_000, 1_000_000_000)?;
+    assert!(logic.set_oracle_half_life(12).is_err());
+
+    logic.context = Context {
+        caller: alkane_id("owner"),
+        ..Default::default()
+    };
+    assert!(logic.set_oracle_half_life(0).is_err());
+    assert!(logic.set_oracle_half_life(MAX_MA_HALF_LIFE as u128 + 1).is_err());
+    let response = logic.set_oracle_half_life(12)?;
+    assert_eq!(
+        SynthPoolEvent::decode(&response.data),
+        Some(SynthPoolEvent::SetOracleHalfLife { half_life: 12 })
+    );
+    assert_eq!(u128_at(&logic.get_oracle_half_life()?.data, 0), 12);
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_twap() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let mut logic = seeded_pool(4_000_000, 0, 1_000_000_000, 1_000_000_000)?;
+    let swap_context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: token_a,
+            value: 100_000_000,
+        }]),
+        ..Default::default()
+    };
+
+    logic.height = 1010;
+    logic.context = swap_context.clone();
+    logic.swap(1, 0)?;
+    let price = u128_at(&logic.last_price(1)?.data, 0);
+    let balance_b = logic.balances(1).to::<u128>();
+
+    logic.height = 1020;
+    let twap = logic.get_twap(20)?.data;
+    assert_eq!(u128_at(&twap, 0), PRECISION);
+    assert_eq!(u128_at(&twap, 1), (10 * PRECISION + 10 * price) / 20);
+    assert_eq!(u128_at(&twap, 2), 1_050_000_000);
+    assert_eq!(u128_at(&twap, 3), (1_000_000_000 + balance_b) / 2);
+
+    // windows inside and across observations
+    assert_eq!(u128_at(&logic.get_twap(5)?.data, 1), price);
+    assert_eq!(u128_at(&logic.get_twap(15)?.data, 1), (5 * PRECISION + 10 * price) / 15);
+
+    let err = logic.get_twap(21).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<SynthPoolError>(),
+        Some(&SynthPoolError::TwapWindowUnavailable { window_blocks: 21, max_window: 20 })
+    );
+    assert!(logic.get_twap(0).is_err());
+
+    // once the ring wraps, only the newest observations remain
+    logic.context.incoming_alkanes = AlkaneTransferParcel(vec![AlkaneTransfer {
+        id: token_a,
+        value: 1_000,
+    }]);
+    for _ in 0..OBSERVATION_SLOTS {
+        logic.height += 1;
+        logic.swap(1, 0)?;
+    }
+    assert_eq!(logic.observation_count(), OBSERVATION_SLOTS);
+    let max_window = (OBSERVATION_SLOTS - 1) as u128;
+    assert!(logic.get_twap(max_window).is_ok());
+    assert!(logic.get_twap(max_window + 1).is_err());
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_directional_fees() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+    let coins = vec![token_a.block, token_a.tx, token_b.block, token_b.tx];
+
+    let mut logic = Logic::<MockStorage>::new().with_height(1000);
+    assert!(logic.init_pool_n(coins.clone(), 100 * 100, 4_000_000, vec![1], vec![], vec![], 0, owner).is_err());
+    assert!(logic.init_pool_n(coins.clone(), 100 * 100, 4_000_000, vec![1, MAX_FEE + 1], vec![], vec![], 0, owner).is_err());
+    // buying token_a costs 0.2%, buying token_b 0.3%
+    logic.init_pool_n(coins, 100 * 100, 4_000_000, vec![20_000_000, 30_000_000], vec![], vec![], 0, owner)?;
+    assert_eq!(logic.get_coin_fees()?.data, [20_000_000u128.to_le_bytes(), 30_000_000u128.to_le_bytes()].concat());
+
+    logic.context = Context {
+        caller: alkane_id("provider"),
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer { id: token_a, value: 1_000_000_000 },
+            AlkaneTransfer { id: token_b, value: 1_000_000_000 },
+        ]),
+        ..Default::default()
+    };
+    logic.add_liquidity(0)?;
+
+    let to_b = logic.get_dy_breakdown(0, 1, 1_000_000)?.data;
+    let to_a = logic.get_dy_breakdown(1, 0, 1_000_000)?.data;
+    let (gross_b, fee_b) = (u128_at(&to_b, 1), u128_at(&to_b, 2));
+    let (gross_a, fee_a) = (u128_at(&to_a, 1), u128_at(&to_a, 2));
+    assert_eq!(fee_b, gross_b * 30_000_000 / FEE_DENOMINATOR);
+    assert_eq!(fee_a, gross_a * 20_000_000 / FEE_DENOMINATOR);
+
+    // exact-out quotes gross up by the output coin's fee too
+    let dy = u128_at(&to_b, 0);
+    let dx = u128_at(&logic.get_dx(0, 1, dy)?.data, 0);
+    assert!(dx >= 1_000_000 && dx <= 1_000_002);
+
+    logic.context = Context {
+        caller: owner,
+        ..Default::default()
+    };
+    logic.commit_new_fee(4_000_000, 0, vec![10_000_000, 10_000_000], 0)?;
+    let pending = logic.get_pending_parameters()?.data;
+    assert_eq!(u128_at(&pending, 4), 10_000_000);
+    assert_eq!(u128_at(&pending, 5), 10_000_000);
+    logic.height += ADMIN_ACTIONS_DELAY;
+    logic.apply_new_fee()?;
+    assert_eq!(logic.coin_fees(0), 10_000_000);
+    assert_eq!(logic.coin_fees(1), 10_000_000);
+
+    // an empty list puts every coin back on the base fee
+    logic.commit_new_fee(4_000_000, 0, vec![], 0)?;
+    logic.height += ADMIN_ACTIONS_DELAY;
+    logic.apply_new_fee()?;
+    assert_eq!(logic.coin_fees(1), 4_000_000);
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_offpeg_fee_multiplier() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let mut logic = seeded_pool(4_000_000, 0, 1_500_000_000, 500_000_000)?;
+    let flat_dy = logic.get_dy_breakdown(0, 1, 10_000_000)?.data;
+    let flat_lp = u128_at(&logic.calc_token_amount_with_fees(vec![10_000_000, 0], 1)?.data, 0);
+    let flat_withdraw = u128_at(&logic.calc_withdraw_one_coin(10_000_000, 1)?.data, 0);
+    assert_eq!(u128_at(&logic.get_dynamic_fee(0, 1)?.data, 0), 4_000_000);
+
+    logic.context = Context {
+        caller: alkane_id("owner"),
+        ..Default::default()
+    };
+    // 4e6 * m may not exceed MAX_FEE
+    let max_multiplier = MAX_FEE * FEE_DENOMINATOR / 4_000_000;
+    let err = logic.commit_new_fee(4_000_000, 0, vec![], max_multiplier + 1).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<SynthPoolError>(),
+        Some(&SynthPoolError::OffpegFeeMultiplierTooHigh {
+            offpeg_fee_multiplier: max_multiplier + 1,
+            max: max_multiplier,
+        })
+    );
+    logic.commit_new_fee(4_000_000, 0, vec![], 5 * FEE_DENOMINATOR)?;
+    logic.height += ADMIN_ACTIONS_DELAY;
+    logic.apply_new_fee()?;
+    assert_eq!(u128_at(&logic.get_offpeg_fee_multiplier()?.data, 0), 5 * FEE_DENOMINATOR);
+
+    // at 3:1 the multiplier raises the fee: 5 / ((5 - 1) * 4 * 0.75 * 0.25 + 1) = 1.25x
+    assert_eq!(u128_at(&logic.get_dynamic_fee(0, 1)?.data, 0), 5_000_000);
+    let dy = logic.get_dy_breakdown(0, 1, 10_000_000)?.data;
+    assert!(u128_at(&dy, 2) > u128_at(&flat_dy, 2));
+    assert!(u128_at(&dy, 0) < u128_at(&flat_dy, 0));
+    assert!(u128_at(&logic.calc_token_amount_with_fees(vec![10_000_000, 0], 1)?.data, 0) < flat_lp);
+    assert!(u128_at(&logic.calc_withdraw_one_coin(10_000_000, 1)?.data, 0) < flat_withdraw);
+
+    // an exact-out quote still buys at least what it promises
+    let dx = u128_at(&logic.get_dx(0, 1, 50_000_000)?.data, 0);
+    assert!(u128_at(&logic.get_dy(0, 1, dx)?.data, 0) >= 50_000_000);
+    assert!(u128_at(&logic.get_dy(0, 1, dx - 5)?.data, 0) < 50_000_000);
+
+    logic.context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: token_a,
+            value: dx,
+        }]),
+        ..Default::default()
+    };
+    let response = logic.swap_exact_out(1, 50_000_000, dx, 0)?;
+    assert_eq!(response.alkanes.0[0].value, 50_000_000);
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_rate_multipliers() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+    let coins = vec![token_a.block, token_a.tx, token_b.block, token_b.tx];
+
+    let mut logic = Logic::<MockStorage>::new().with_height(1000);
+    let err = logic.init_pool_n(coins.clone(), 100 * 100, 4_000_000, vec![], vec![8, 19], vec![], 0, owner).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<SynthPoolError>(),
+        Some(&SynthPoolError::DecimalsOutOfRange { index: 1, decimals: 19, max: 18 })
+    );
+    assert!(logic.init_pool_n(coins.clone(), 100 * 100, 4_000_000, vec![], vec![8], vec![], 0, owner).is_err());
+    // token_b has 18 decimals
+    logic.init_pool_n(coins, 100 * 100, 4_000_000, vec![], vec![8, 18], vec![], 0, owner)?;
+    assert_eq!(logic.get_rates()?.data, [PRECISION.to_le_bytes(), 10u128.pow(8).to_le_bytes()].concat());
+
+    logic.context = Context {
+        caller: alkane_id("provider"),
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer { id: token_a, value: 10u128.pow(9) },
+            AlkaneTransfer { id: token_b, value: 10u128.pow(19) },
+        ]),
+        ..Default::default()
+    };
+    // ten of each coin are worth 20 in LP, at 8 decimals
+    let response = logic.add_liquidity(0)?;
+    assert_eq!(lp_amount(&logic, &response), 2 * 10u128.pow(9));
+    assert_eq!(u128_at(&logic.get_spot_price(0, 1)?.data, 0), 10u128.pow(28));
+    assert_eq!(u128_at(&logic.get_spot_price(1, 0)?.data, 0), 10u128.pow(8));
+
+    // quotes match an 8-decimal pool of the same value
+    let reference = seeded_pool(4_000_000, 0, 10u128.pow(9), 10u128.pow(9))?;
+    let dy = u128_at(&reference.get_dy(0, 1, 1_000_000)?.data, 0);
+    let dy_b = u128_at(&logic.get_dy(0, 1, 1_000_000)?.data, 0);
+    assert!(dy_b <= dy * 10u128.pow(10) && dy_b >= (dy - 1) * 10u128.pow(10));
+    let dy_a = u128_at(&logic.get_dy(1, 0, 10u128.pow(16))?.data, 0);
+    assert_eq!(dy_a, dy);
+
+    let dx = u128_at(&logic.get_dx(1, 0, 1_000_000)?.data, 0);
+    assert!(u128_at(&logic.get_dy(1, 0, dx)?.data, 0) >= 1_000_000);
+    logic.context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer { id: token_b, value: dx }]),
+        ..Default::default()
+    };
+    let response = logic.swap_exact_out(0, 1_000_000, dx, 0)?;
+    assert_eq!(response.alkanes.0[0].value, 1_000_000);
+    assert_eq!(logic.balances(1), U256::from(10u128.pow(19) + dx));
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_rate_oracle() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+    let coins = vec![token_a.block, token_a.tx, token_b.block, token_b.tx];
+
+    let mut logic = Logic::<MockStorage>::new().with_height(1000);
+    // token_b reports its rate through opcode 104
+    logic.init_pool_n(coins, 100 * 100, 4_000_000, vec![], vec![], vec![0, 104], 0, owner)?;
+    assert_eq!(logic.rate_oracles(1), 104);
+    let err = logic.get_rates().unwrap_err();
+    assert_eq!(err.downcast_ref::<SynthPoolError>(), Some(&SynthPoolError::RateUnavailable { index: 1 }));
+
+    logic.oracle_rates.insert(1, U256::from(11 * PRECISION / 10));
+    assert_eq!(logic.get_rates()?.data, [PRECISION.to_le_bytes(), (11 * PRECISION / 10).to_le_bytes()].concat());
+    logic.context = Context {
+        caller: alkane_id("provider"),
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer { id: token_a, value: 1_100_000_000 },
+            AlkaneTransfer { id: token_b, value: 1_000_000_000 },
+        ]),
+        ..Default::default()
+    };
+    logic.add_liquidity(0)?;
+    // balanced by value, so token_b trades at its rate
+    assert_eq!(u128_at(&logic.get_spot_price(1, 0)?.data, 0), 11 * PRECISION / 10);
+    let dy = u128_at(&logic.get_dy(0, 1, 1_100_000)?.data, 0);
+    assert!(dy > 999_000 && dy < 1_000_000);
+    let dx = u128_at(&logic.get_dx(0, 1, 500_000)?.data, 0);
+    assert!(u128_at(&logic.get_dy(0, 1, dx)?.data, 0) >= 500_000);
+
+    // yield accruing to token_b lifts the virtual price
+    let virtual_price = u128_at(&logic.get_virtual_price()?.data, 0);
+    logic.oracle_rates.insert(1, U256::from(12 * PRECISION / 10));
+    assert!(u128_at(&logic.get_virtual_price()?.data, 0) > virtual_price);
+
+    // without a rate the pool cannot price token_b
+    logic.oracle_rates.clear();
+    let err = logic.get_dy(0, 1, 1_100_000).unwrap_err();
+    assert_eq!(err.downcast_ref::<SynthPoolError>(), Some(&SynthPoolError::RateUnavailable { index: 1 }));
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_remove_liquidity_without_rate() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+    let provider = alkane_id("provider");
+    let coins = vec![token_a.block, token_a.tx, token_b.block, token_b.tx];
+
+    let mut logic = Logic::<MockStorage>::new().with_height(1000);
+    logic.init_pool_n(coins, 100 * 100, 4_000_000, vec![], vec![], vec![0, 104], 0, owner)?;
+    logic.oracle_rates.insert(1, U256::from(PRECISION));
+    logic.context = Context {
+        caller: provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer { id: token_a, value: 1_000_000_000 },
+            AlkaneTransfer { id: token_b, value: 1_000_000_000 },
+        ]),
+        ..Default::default()
+    };
+    let response = logic.add_liquidity(0)?;
+    let lp_balance = lp_amount(&logic, &response);
+    let last_price = logic.last_prices(1);
+    let (price_ema, ma_last_height) = (logic.price_emas(1), logic.ma_last_height());
+
+    // a broken oracle stops trading but not the balanced exit, even once killed
+    logic.oracle_rates.clear();
+    logic.context = Context { caller: owner, ..Default::default() };
+    logic.kill_me()?;
+    logic.height += 10;
+    logic.context = Context {
+        caller: provider,
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer { id: logic.context.myself, value: lp_balance / 2 }]),
+        ..Default::default()
+    };
+    let err = logic.remove_liquidity_one_coin(0, 0).unwrap_err();
+    assert_eq!(err.downcast_ref::<SynthPoolError>(), Some(&SynthPoolError::Killed {}));
+    let response = logic.remove_liquidity(vec![0, 0])?;
+    assert_eq!(response.alkanes.0[0].value, 500_000_000);
+    assert_eq!(response.alkanes.0[1].value, 500_000_000);
+    assert_eq!(logic.balances(1), U256::from(500_000_000u128));
+    // the oracle upkeep is skipped as a whole, not left half done
+    assert_eq!(logic.last_prices(1), last_price);
+    assert_eq!(logic.price_emas(1), price_ema);
+    assert_eq!(logic.ma_last_height(), ma_last_height);
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_rate_oracles_read_only_when_pricing() -> Result<()> {
+    // rates are read for calls that price the coins, not for plain views
+    for (opcode, inputs) in [(5, vec![1, 0]), (35, vec![1, 0, 0]), (2, vec![0, 0]), (200, vec![]), (223, vec![])] {
+        assert!(SynthPoolMessage::from_opcode(opcode, inputs)?.prices_coins(), "opcode {}", opcode);
+    }
+    for (opcode, inputs) in [(99, vec![]), (100, vec![]), (201, vec![]), (204, vec![]), (1001, vec![]), (1002, vec![2, 1])] {
+        assert!(!SynthPoolMessage::from_opcode(opcode, inputs)?.prices_coins(), "opcode {}", opcode);
+    }
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_extreme_balances() -> Result<()> {
+    let token_a = alkane_id("token_a");
+
+    // near-u128::MAX deposits are turned away before any maths runs
+    let err = seeded_pool(4_000_000, 0, u128::MAX, u128::MAX).err().unwrap();
+    assert_eq!(
+        err.downcast_ref::<SynthPoolError>(),
+        Some(&SynthPoolError::PoolTooLarge { total: u128::MAX, max: math::MAX_XP_SUM })
+    );
+
+    // a pool at the bound still prices correctly
+    let mut logic = seeded_pool(4_000_000, 0, math::MAX_XP_SUM / 2, math::MAX_XP_SUM / 2)?;
+    assert_eq!(u128_at(&logic.get_virtual_price()?.data, 0), PRECISION);
+    let withdrawn = u128_at(&logic.calc_withdraw_one_coin(logic.total_supply() / 2, 1)?.data, 0);
+    assert!(withdrawn > math::MAX_XP_SUM / 4 && withdrawn < math::MAX_XP_SUM / 2);
+    let err = logic.get_dy(0, 1, 1).unwrap_err();
+    assert!(matches!(err.downcast_ref::<SynthPoolError>(), Some(SynthPoolError::PoolTooLarge { .. })));
+    // swapping u128::MAX in fails the same way instead of overflowing
+    logic.context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer { id: token_a, value: u128::MAX }]),
+        ..Default::default()
+    };
+    let err = logic.swap(1, 0).unwrap_err();
+    assert!(matches!(err.downcast_ref::<SynthPoolError>(), Some(SynthPoolError::PoolTooLarge { .. })));
+
+    // one side near zero: the empty side is worth nothing more to withdraw,
+    // and a little of it buys nearly all of the other side
+    let logic = seeded_pool(4_000_000, 0, math::MAX_XP_SUM / 10, 1)?;
+    assert_eq!(u128_at(&logic.get_dy(0, 1, 10u128.pow(20))?.data, 0), 0);
+    let dy = u128_at(&logic.get_dy(1, 0, 10u128.pow(20))?.data, 0);
+    assert!(dy > math::MAX_XP_SUM / 11 && dy < math::MAX_XP_SUM / 10);
+    assert_eq!(u128_at(&logic.calc_withdraw_one_coin(logic.total_supply() / 2, 1)?.data, 0), 0);
+    // past where D converges, the deposit fails with a typed error
+    let err = seeded_pool(4_000_000, 0, math::MAX_XP_SUM / 2, 1).err().unwrap();
+    assert_eq!(err.downcast_ref::<SynthPoolError>(), Some(&SynthPoolError::DDidNotConverge {}));
+
+    // the curve maths itself reports overflow, empty balances and bad indices
+    let amp = U256::from(100 * 100);
+    assert_eq!(math::get_D(&[U256::MAX, U256::from(1)], amp).unwrap_err(), SynthPoolError::MathOverflow {});
+    let xp = [U256::from(1_000), U256::ZERO, U256::from(1_000)];
+    assert_eq!(math::get_y(0, 2, U256::from(1_000), &xp, amp, U256::from(2_000)).unwrap_err(), SynthPoolError::DivisionByZero {});
+    assert_eq!(math::get_y(1, 1, U256::from(1_000), &xp, amp, U256::from(2_000)).unwrap_err(), SynthPoolError::SameCoin { index: 1 });
+    assert_eq!(math::get_y_D(amp, 3, &xp, U256::from(2_000)).unwrap_err(), SynthPoolError::CoinIndexOutOfRange { index: 3, n_coins: 3 });
+    assert_eq!(math::to_u128(U256::from(u128::MAX) + U256::from(1)), Err(SynthPoolError::MathOverflow {}));
+
+    // LP supply cannot wrap
+    let mut logic = Logic::<MockStorage>::new();
+    logic.set_total_supply(u128::MAX);
+    let err = logic.mint(1).unwrap_err();
+    assert_eq!(err.downcast_ref::<SynthPoolError>(), Some(&SynthPoolError::MathOverflow {}));
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_pool_state() -> Result<()> {
+    let token_a = alkane_id("token_a");
+    let token_b = alkane_id("token_b");
+    let owner = alkane_id("owner");
+    let coins = vec![token_a.block, token_a.tx, token_b.block, token_b.tx];
+
+    // an off-peg pool with 18-decimal token_b, so rates and the dynamic fee both matter
+    let mut logic = Logic::<MockStorage>::new().with_height(1000);
+    logic.init_pool_n(coins, 100 * 100, 4_000_000, vec![], vec![8, 18], vec![], 5_000_000_000, owner)?;
+    logic.context = Context {
+        caller: alkane_id("provider"),
+        incoming_alkanes: AlkaneTransferParcel(vec![
+            AlkaneTransfer { id: token_a, value: 3 * 10u128.pow(9) },
+            AlkaneTransfer { id: token_b, value: 10u128.pow(19) },
+        ]),
+        ..Default::default()
+    };
+    logic.add_liquidity(0)?;
+    logic.context = Context {
+        caller: owner,
+        ..Default::default()
+    };
+    logic.commit_new_fee(4_000_000, 5_000_000_000, vec![], 5 * FEE_DENOMINATOR)?;
+    logic.height += ADMIN_ACTIONS_DELAY;
+    logic.apply_new_fee()?;
+
+    let state = PoolState::decode(&logic.get_pool_state()?.data).unwrap();
+    assert_eq!(state.balances, vec![U256::from(3 * 10u128.pow(9)), U256::from(10u128.pow(19))]);
+    assert_eq!(state.rates, vec![U256::from(PRECISION), U256::from(10u128.pow(8))]);
+    assert_eq!(state.total_supply, U256::from(logic.total_supply()));
+
+    // quotes from the decoded state match the contract's to the unit
+    let (dy, dy_fee, dy_admin_fee) = state.get_dy(0, 1, U256::from(10u128.pow(8)))?;
+    let breakdown = logic.get_dy_breakdown(0, 1, 10u128.pow(8))?.data;
+    assert_eq!(U256::from(u128_at(&breakdown, 0)), dy);
+    assert_eq!(U256::from(u128_at(&breakdown, 2) + u128_at(&breakdown, 3)), dy_fee);
+    assert_eq!(U256::from(u128_at(&breakdown, 3)), dy_admin_fee);
+    let dx = state.get_dx(1, 0, U256::from(10u128.pow(8)))?;
+    assert_eq!(U256::from(u128_at(&logic.get_dx(1, 0, 10u128.pow(8))?.data, 0)), dx);
+    assert_eq!(U256::from(u128_at(&logic.get_spot_price(0, 1)?.data, 0)), state.spot_price(0, 1)?);
+    assert_eq!(U256::from(u128_at(&logic.get_dynamic_fee(0, 1)?.data, 0)), state.swap_fee(0, 1)?);
+    let amounts = [U256::from(10u128.pow(8)), U256::ZERO];
+    let (lp, _) = state.calc_token_amount(&amounts, true, true)?;
+    assert_eq!(U256::from(u128_at(&logic.calc_token_amount_with_fees(vec![10u128.pow(8), 0], 1)?.data, 0)), lp);
+    let (withdrawn, _) = state.calc_withdraw_one_coin(U256::from(10u128.pow(8)), 1)?;
+    assert_eq!(U256::from(u128_at(&logic.calc_withdraw_one_coin(10u128.pow(8), 1)?.data, 0)), withdrawn);
+    assert_eq!(logic.get_virtual_price()?.data, state.virtual_price()?.to_le_bytes_vec());
+
+    // and so does the trade itself
+    logic.context = Context {
+        caller: alkane_id("swapper"),
+        incoming_alkanes: AlkaneTransferParcel(vec![AlkaneTransfer { id: token_a, value: 10u128.pow(8) }]),
+        ..Default::default()
+    };
+    let response = logic.swap(1, 0)?;
+    assert_eq!(U256::from(response.alkanes.0[0].value), dy);
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_sdk_round_trip() -> Result<()> {
+    use slope_sdk::{view, SynthPoolCall as Call};
+    let id = |block, tx| slope_sdk::AlkaneId::new(block, tx);
+    let calls = vec![
+        Call::InitPool { token_a: id(2, 1), token_b: id(2, 3), A: 10_000, fee: 4_000_000, admin_fee: 5, owner: id(2, 9) },
+        Call::AddLiquidity { min_mint_amount: 1 },
+        Call::RemoveLiquidity { min_amounts: vec![1, 2, 3] },
+        Call::RemoveLiquidityOneCoin { i: 1, min_amount: 2 },
+        Call::RemoveLiquidityImbalance { amounts: vec![1, 2], max_burn_amount: 3 },
+        Call::Swap { j: 1, min_dy: 2 },
+        Call::InitPoolN {
+            coins: vec![2, 1, 2, 3],
+            A: 10_000,
+            fee: 4_000_000,
+            coin_fees: vec![1, 2],
+            decimals: vec![8, 18],
+            rate_oracles: vec![0, 103],
+            admin_fee: 5,
+            owner: id(2, 9),
+        },
+        Call::SwapExactOut { j: 1, dy: 2, max_dx: 3, deadline: 4 },
+        Call::ClaimAdminFees {},
+        Call::RampA { future_a: 1, future_height: 2 },
+        Call::StopRampA {},
+        Call::CommitNewFee { fee: 1, admin_fee: 2, coin_fees: vec![3], offpeg_fee_multiplier: 4 },
+        Call::ApplyNewFee {},
+        Call::RevertNewParameters {},
+        Call::CommitTransferOwnership { new_owner: id(2, 9) },
+        Call::ApplyTransferOwnership {},
+        Call::RevertTransferOwnership {},
+        Call::RenounceOwnership {},
+        Call::KillMe {},
+        Call::UnkillMe {},
+        Call::SetOracleHalfLife { half_life: 1 },
+        Call::AddLiquidityWithDeadline { min_mint_amount: 1, deadline: 2 },
+        Call::RemoveLiquidityWithDeadline { min_amounts: vec![1, 2, 3], deadline: 4 },
+        Call::RemoveLiquidityOneCoinWithDeadline { i: 1, min_amount: 2, deadline: 3 },
+        Call::RemoveLiquidityImbalanceWithDeadline { amounts: vec![1, 2], max_burn_amount: 3, deadline: 4 },
+        Call::SwapWithDeadline { j: 1, min_dy: 2, deadline: 3 },
+        Call::Forward {},
+        Call::GetName {},
+        Call::GetSymbol {},
+        Call::GetTotalSupply {},
+        Call::GetVirtualPrice {},
+        Call::GetBalances {},
+        Call::GetA {},
+        Call::GetPendingParameters {},
+        Call::GetOwner {},
+        Call::IsKilled {},
+        Call::GetDy { i: 1, j: 2, dx: 3 },
+        Call::GetDyBreakdown { i: 1, j: 2, dx: 3 },
+        Call::GetDx { i: 1, j: 2, dy: 3 },
+        Call::GetSpotPrice { i: 1, j: 2 },
+        Call::GetPriceImpact { i: 1, j: 2, dx: 3 },
+        Call::CalcTokenAmount { amounts: vec![1, 2], is_deposit: 1 },
+        Call::CalcTokenAmountWithFees { amounts: vec![1, 2], is_deposit: 0 },
+        Call::CalcWithdrawOneCoin { token_amount: 1, i: 2 },
+        Call::CalcWithdrawOneCoinWithFee { token_amount: 1, i: 2 },
+        Call::PriceOracle { i: 1 },
+        Call::LastPrice { i: 1 },
+        Call::EmaVirtualPrice {},
+        Call::GetOracleHalfLife {},
+        Call::GetTwap { window_blocks: 1 },
+        Call::GetCoinFees {},
+        Call::GetOffpegFeeMultiplier {},
+        Call::GetDynamicFee { i: 1, j: 2 },
+        Call::GetRates {},
+        Call::GetPoolState {},
+        Call::GetDecimals {},
+        Call::GetBalanceOf { owner: id(2, 9) },
+    ];
+    // the contract parses every call back into the same message
+    for call in calls.iter() {
+        let inputs = call.inputs();
+        let message = SynthPoolMessage::from_opcode(inputs[0], inputs[1..].to_vec())?;
+        assert_eq!(format!("{:?}", message), format!("{:?}", call));
+        assert_eq!(Call::decode(&inputs).as_ref(), Some(call));
+    }
+    // and accepts no opcode the SDK cannot build
+    for opcode in 0..=1002 {
+        if SynthPoolMessage::from_opcode(opcode, vec![0; 16]).is_ok() {
+            assert!(calls.iter().any(|call| call.opcode() == opcode), "opcode {} missing from the SDK", opcode);
+        }
+    }
+
+    // views decode from the contract's own responses
+    let logic = seeded_pool(4_000_000, 5_000_000_000, 1_000_000_000, 1_000_000_000)?;
+    let balances = view::get_balances(&logic.get_balances()?.data).unwrap();
+    assert_eq!(balances, vec![U256::from(1_000_000_000u128); 2]);
+    assert_eq!(view::get_owner(&logic.get_owner()?.data), Some(id(alkane_id("owner").block, alkane_id("owner").tx)));
+    assert_eq!(view::is_killed(&logic.is_killed()?.data), Some(false));
+    assert_eq!(view::get_virtual_price(&logic.get_virtual_price()?.data), Some(U256::from(PRECISION)));
+    let breakdown = view::get_dy_breakdown(&logic.get_dy_breakdown(0, 1, 10_000_000)?.data).unwrap();
+    assert_eq!(view::get_dy(&logic.get_dy(0, 1, 10_000_000)?.data), Some(breakdown.dy));
+    assert_eq!(breakdown.dy_gross, breakdown.dy + breakdown.lp_fee + breakdown.admin_fee);
+    let (lp, fees) = view::calc_token_amount_with_fees(&logic.calc_token_amount_with_fees(vec![10_000_000, 0], 1)?.data).unwrap();
+    assert!(lp > 0 && fees.len() == 2);
+    let pending = view::get_pending_parameters(&logic.get_pending_parameters()?.data).unwrap();
+    assert_eq!(pending.coin_fees.len(), 2);
+    let state = view::get_pool_state(&logic.get_pool_state()?.data).unwrap();
+    assert_eq!(state.balances, balances);
+    Ok(())
+}
@@ -0,0 +1,150 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+use std::cell::RefCell;
+
+use anyhow::{anyhow, bail, Context, Result};
+use slope_sdk::AlkaneId;
+
+/// Command line: a subcommand, `--name value` options and the `--json` and
+/// `--help` switches. Options may repeat; lists are comma-separated.
+#[derive(Debug, Default)]
+pub struct Args {
+    pub command: String,
+    pub json: bool,
+    pub help: bool,
+    options: Vec<(String, String)>,
+    read: RefCell<Vec<String>>,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                if !parsed.command.is_empty() {
+                    bail!("Unexpected argument {}", arg);
+                }
+                parsed.command = arg;
+                continue;
+            };
+            match name {
+                "json" => parsed.json = true,
+                "help" => parsed.help = true,
+                _ => {
+                    let (name, value) = match name.split_once('=') {
+                        Some((name, value)) => (name.to_string(), value.to_string()),
+                        None => {
+                            let value = args.next().ok_or_else(|| anyhow!("--{} needs a value", name))?;
+                            (name.to_string(), value)
+                        }
+                    };
+                    parsed.options.push((name, value));
+                }
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Every value given for `name`, with lists split on commas
+    pub fn all(&self, name: &str) -> Vec<&str> {
+        self.read.borrow_mut().push(name.to_string());
+        self.options
+            .iter()
+            .filter(|(option, _)| option == name)
+            .flat_map(|(_, value)| value.split(','))
+            .filter(|value| !value.is_empty())
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.read.borrow_mut().push(name.to_string());
+        self.options.iter().rev().find(|(option, _)| option == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn required(&self, name: &str) -> Result<&str> {
+        self.get(name).ok_or_else(|| anyhow!("--{} is required", name))
+    }
+
+    pub fn u128(&self, name: &str) -> Result<Option<u128>> {
+        self.get(name).map(|value| parse_u128(name, value)).transpose()
+    }
+
+    pub fn required_u128(&self, name: &str) -> Result<u128> {
+        parse_u128(name, self.required(name)?)
+    }
+
+    pub fn u128_list(&self, name: &str) -> Result<Vec<u128>> {
+        self.all(name).into_iter().map(|value| parse_u128(name, value)).collect()
+    }
+
+    pub fn id(&self, name: &str) -> Result<AlkaneId> {
+        parse_id(self.required(name)?)
+    }
+
+    /// Fails on options no command read, which are most likely typos
+    pub fn check_unused(&self) -> Result<()> {
+        let read = self.read.borrow();
+        match self.options.iter().find(|(option, _)| !read.contains(option)) {
+            Some((option, _)) => bail!("Option --{} does not apply to {}", option, self.command),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_u128(name: &str, value: &str) -> Result<u128> {
+    value.replace('_', "").parse().with_context(|| format!("--{}: {} is not an amount", name, value))
+}
+
+/// `block:tx`
+pub fn parse_id(value: &str) -> Result<AlkaneId> {
+    let (block, tx) = value.split_once(':').ok_or_else(|| anyhow!("{} is not an alkane id (block:tx)", value))?;
+    Ok(AlkaneId::new(parse_u128("id", block)?, parse_u128("id", tx)?))
+}
+
+/// `block:tx:amount`
+pub fn parse_transfer(value: &str) -> Result<(AlkaneId, u128)> {
+    let (id, amount) = value.rsplit_once(':').ok_or_else(|| anyhow!("{} is not block:tx:amount", value))?;
+    Ok((parse_id(id)?, parse_u128("amount", amount)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Result<Args> {
+        Args::parse(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = args("swap --pool 2:21 --sell 2:1:1_000 --to=1 --json --min-dy 990").unwrap();
+        assert_eq!(args.command, "swap");
+        assert!(args.json);
+        assert_eq!(args.id("pool").unwrap(), AlkaneId::new(2, 21));
+        assert_eq!(parse_transfer(args.required("sell").unwrap()).unwrap(), (AlkaneId::new(2, 1), 1_000));
+        assert_eq!(args.required_u128("to").unwrap(), 1);
+        assert!(args.check_unused().is_err());
+        assert_eq!(args.u128("min-dy").unwrap(), Some(990));
+        args.check_unused().unwrap();
+
+        let args = self::args("add-liquidity --deposit 2:1:5,2:3:6 --deposit 2:5:7").unwrap();
+        assert_eq!(args.all("deposit"), ["2:1:5", "2:3:6", "2:5:7"]);
+        assert!(self::args("a --pool").is_err());
+        assert!(self::args("a b").is_err());
+        assert!(parse_id("2").is_err());
+    }
+}
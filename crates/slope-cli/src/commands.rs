@@ -0,0 +1,424 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+//! Views decode the pool's response. Calls that change state are simulated
+//! with the alkanes they consume, reporting what the pool would emit and
+//! return, and with `--utxos` are also built into an unsigned PSBT.
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Map, Value};
+use slope_sdk::{math, view, AlkaneId, SynthPoolCall, SynthPoolEvent};
+
+use crate::args::{parse_id, parse_transfer, Args};
+use crate::rpc::{self, Rpc};
+use crate::tx;
+
+pub const USAGE: &str = "\
+Usage: slope-cli <command> --pool BLOCK:TX [options] [--json]
+
+Views:
+  balances
+  virtual-price
+  a
+  quote --from I --to J --dx AMOUNT
+
+Calls, simulated and, given --utxos FILE --recipient HEX [--change HEX]
+[--fee-rate SAT_PER_VB], built into an unsigned PSBT. Simulations do not run
+as the wallet, so with --utxos a reverted one is only a warning:
+  init-pool --coins ID,ID[,..] --a A --fee FEE --admin-fee FEE --owner ID
+            [--coin-fees F,..] [--decimals D,..] [--rate-oracles OPCODE,..]
+            (one rate oracle opcode per coin, called on the coin itself; 0 for none)
+  add-liquidity --deposit ID:AMOUNT[,..] --min-mint AMOUNT [--deadline HEIGHT]
+  remove-liquidity --lp AMOUNT
+            (--min-amounts A,.. | --one-coin I --min-amount A | --imbalance A,.. --max-burn A)
+            [--deadline HEIGHT]
+  swap --sell ID:AMOUNT --to J --min-dy AMOUNT [--deadline HEIGHT]
+  claim-admin-fees    (must be sent by the pool owner)
+
+Options:
+  --rpc URL    alkanes JSON-RPC endpoint, defaults to $SLOPE_RPC or http://localhost:18888
+  --json       print JSON instead of text";
+
+/// A reported value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    Amount(String),
+    /// Scaled by 1e18
+    Price(String),
+    Text(String),
+    List(Vec<Field>),
+}
+
+impl Field {
+    fn amount(value: impl ToString) -> Self {
+        Self::Amount(value.to_string())
+    }
+
+    fn price(value: impl ToString) -> Self {
+        Self::Price(value.to_string())
+    }
+
+    fn human(&self) -> String {
+        match self {
+            Self::Amount(value) | Self::Text(value) => value.clone(),
+            Self::Price(value) => decimal(value, 18),
+            Self::List(fields) => fields.iter().map(Self::human).collect::<Vec<_>>().join(", "),
+        }
+    }
+
+    /// Amounts stay strings, as u128s overflow JSON numbers
+    fn json(&self) -> Value {
+        match self {
+            Self::Amount(value) | Self::Price(value) | Self::Text(value) => json!(value),
+            Self::List(fields) => Value::Array(fields.iter().map(Self::json).collect()),
+        }
+    }
+}
+
+/// `value / 10^scale` without trailing zeros
+fn decimal(value: &str, scale: usize) -> String {
+    let padded = format!("{:0>width$}", value, width = scale + 1);
+    let (int, frac) = padded.split_at(padded.len() - scale);
+    let frac = frac.trim_end_matches('0');
+    if frac.is_empty() {
+        int.to_string()
+    } else {
+        format!("{}.{}", int, frac)
+    }
+}
+
+fn id_text(id: AlkaneId) -> String {
+    format!("{}:{}", id.block, id.tx)
+}
+
+/// What a command prints, one named field per line
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Report(pub Vec<(&'static str, Field)>);
+
+impl Report {
+    fn push(&mut self, name: &'static str, field: Field) {
+        self.0.push((name, field));
+    }
+
+    pub fn render(&self, json: bool) -> String {
+        if json {
+            let object: Map<String, Value> =
+                self.0.iter().map(|(name, field)| (name.to_string(), field.json())).collect();
+            Value::Object(object).to_string()
+        } else {
+            self.0.iter().map(|(name, field)| format!("{}: {}", name, field.human())).collect::<Vec<_>>().join("\n")
+        }
+    }
+}
+
+fn decoded<T>(value: Option<T>, call: &SynthPoolCall) -> Result<T> {
+    value.ok_or_else(|| anyhow!("Malformed response to opcode {}", call.opcode()))
+}
+
+/// A parsed command
+enum Action {
+    Balances,
+    VirtualPrice,
+    A,
+    Quote { i: u128, j: u128, dx: u128 },
+    /// A call changing state, with the alkanes it consumes
+    Call { call: SynthPoolCall, transfers: Vec<(AlkaneId, u128)>, wallet: Option<Box<tx::Wallet>> },
+}
+
+fn parse(args: &Args, pool: AlkaneId) -> Result<Action> {
+    let (call, transfers) = match args.command.as_str() {
+        "balances" => return Ok(Action::Balances),
+        "virtual-price" => return Ok(Action::VirtualPrice),
+        "a" => return Ok(Action::A),
+        "quote" => {
+            return Ok(Action::Quote {
+                i: args.required_u128("from")?,
+                j: args.required_u128("to")?,
+                dx: args.required_u128("dx")?,
+            })
+        }
+        "init-pool" => {
+            let ids = |name| -> Result<Vec<u128>> {
+                let ids = args.all(name).into_iter().map(parse_id).collect::<Result<Vec<_>>>()?;
+                Ok(ids.into_iter().flat_map(|id| [id.block, id.tx]).collect())
+            };
+            let coins = ids("coins")?;
+            let rate_oracles = args.u128_list("rate-oracles")?;
+            if !rate_oracles.is_empty() && rate_oracles.len() != coins.len() / 2 {
+                bail!("--rate-oracles takes one opcode per coin, {} for {} coins", rate_oracles.len(), coins.len() / 2);
+            }
+            let call = SynthPoolCall::InitPoolN {
+                coins,
+                A: args.required_u128("a")?,
+                fee: args.required_u128("fee")?,
+                coin_fees: args.u128_list("coin-fees")?,
+                decimals: args.u128_list("decimals")?,
+                rate_oracles,
+                admin_fee: args.required_u128("admin-fee")?,
+                owner: args.id("owner")?,
+            };
+            (call, vec![])
+        }
+        "add-liquidity" => {
+            let deposits = args.all("deposit").into_iter().map(parse_transfer).collect::<Result<Vec<_>>>()?;
+            if deposits.is_empty() {
+                bail!("--deposit is required");
+            }
+            let call = SynthPoolCall::AddLiquidityWithDeadline {
+                min_mint_amount: args.required_u128("min-mint")?,
+                deadline: args.u128("deadline")?.unwrap_or(0),
+            };
+            (call, deposits)
+        }
+        "remove-liquidity" => {
+            let deadline = args.u128("deadline")?.unwrap_or(0);
+            let lp = args.required_u128("lp")?;
+            let call = if let Some(i) = args.u128("one-coin")? {
+                SynthPoolCall::RemoveLiquidityOneCoinWithDeadline { i, min_amount: args.required_u128("min-amount")?, deadline }
+            } else if args.get("imbalance").is_some() {
+                SynthPoolCall::RemoveLiquidityImbalanceWithDeadline {
+                    amounts: args.u128_list("imbalance")?,
+                    max_burn_amount: args.required_u128("max-burn")?,
+                    deadline,
+                }
+            } else {
+                SynthPoolCall::RemoveLiquidityWithDeadline { min_amounts: args.u128_list("min-amounts")?, deadline }
+            };
+            // the pool is its own LP token
+            (call, vec![(pool, lp)])
+        }
+        "swap" => {
+            let call = SynthPoolCall::SwapWithDeadline {
+                j: args.required_u128("to")?,
+                min_dy: args.required_u128("min-dy")?,
+                deadline: args.u128("deadline")?.unwrap_or(0),
+            };
+            (call, vec![parse_transfer(args.required("sell")?)?])
+        }
+        "claim-admin-fees" => (SynthPoolCall::ClaimAdminFees {}, vec![]),
+        command => bail!("Unknown command {:?}\n\n{}", command, USAGE),
+    };
+    Ok(Action::Call { call, transfers, wallet: tx::Wallet::from_args(args)?.map(Box::new) })
+}
+
+fn view(rpc: &dyn Rpc, pool: AlkaneId, call: SynthPoolCall) -> Result<(Vec<u8>, SynthPoolCall)> {
+    Ok((rpc::view(rpc, pool, &call)?, call))
+}
+
+pub fn run(rpc: &dyn Rpc, args: &Args) -> Result<Report> {
+    let pool = args.id("pool")?;
+    let action = parse(args, pool)?;
+    args.check_unused()?;
+
+    let mut report = Report::default();
+    match action {
+        Action::Balances => {
+            let (data, call) = view(rpc, pool, SynthPoolCall::GetBalances {})?;
+            let balances = decoded(view::get_balances(&data), &call)?;
+            report.push("balances", Field::List(balances.iter().map(Field::amount).collect()));
+        }
+        Action::VirtualPrice => {
+            let (data, call) = view(rpc, pool, SynthPoolCall::GetVirtualPrice {})?;
+            report.push("virtual_price", Field::price(decoded(view::get_virtual_price(&data), &call)?));
+        }
+        Action::A => {
+            let (data, call) = view(rpc, pool, SynthPoolCall::GetA {})?;
+            let a = decoded(view::get_a(&data), &call)?;
+            report.push("a", Field::amount(a / math::A_PRECISION));
+            report.push("a_precise", Field::amount(a));
+        }
+        Action::Quote { i, j, dx } => {
+            let (data, call) = view(rpc, pool, SynthPoolCall::GetDyBreakdown { i, j, dx })?;
+            let breakdown = decoded(view::get_dy_breakdown(&data), &call)?;
+            let (data, call) = view(rpc, pool, SynthPoolCall::GetPriceImpact { i, j, dx })?;
+            let impact = decoded(view::get_price_impact(&data), &call)?;
+            report.push("dy", Field::amount(breakdown.dy));
+            report.push("dy_gross", Field::amount(breakdown.dy_gross));
+            report.push("lp_fee", Field::amount(breakdown.lp_fee));
+            report.push("admin_fee", Field::amount(breakdown.admin_fee));
+            report.push("spot_price", Field::price(impact.spot_price));
+            report.push("execution_price", Field::price(impact.execution_price));
+            report.push("price_impact", Field::price(impact.impact));
+        }
+        Action::Call { call, transfers, wallet } => {
+            let height = rpc::height(rpc)?;
+            let simulation = rpc::simulate(rpc, pool, &call, &transfers, height)?;
+            let returned = simulation.alkanes.clone();
+            match simulation.into_data() {
+                Ok(data) => {
+                    if let Some(event) = SynthPoolEvent::decode(&data) {
+                        report.push("event", Field::Text(format!("{:?}", event)));
+                    }
+                    let returned =
+                        returned.iter().map(|&(id, amount)| Field::Text(format!("{}:{}", id_text(id), amount)));
+                    report.push("returned", Field::List(returned.collect()));
+                }
+                // the simulation cannot run as the wallet, so owner-only
+                // calls revert there; the PSBT is still worth building
+                Err(err) if wallet.is_some() => report.push("warning", Field::Text(format!("{:#}", err))),
+                Err(err) => return Err(err),
+            }
+            if let Some(wallet) = wallet {
+                report.push("psbt", Field::Text(wallet.build_psbt(pool, &call, &transfers)?));
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use slope_sdk::{SynthPoolError, U256};
+
+    use super::*;
+
+    /// Answers `metashrew_height` with 100 and `alkanes_simulate` with the
+    /// next canned execution, recording the simulated inputs
+    struct MockRpc {
+        executions: RefCell<Vec<Value>>,
+        requests: RefCell<Vec<Value>>,
+    }
+
+    impl MockRpc {
+        fn new(executions: Vec<Value>) -> Self {
+            Self { executions: RefCell::new(executions), requests: RefCell::default() }
+        }
+
+        fn inputs(&self) -> Vec<Value> {
+            self.requests.borrow().iter().map(|request| request[0]["inputs"].clone()).collect()
+        }
+    }
+
+    impl Rpc for MockRpc {
+        fn call(&self, method: &str, params: Value) -> Result<Value> {
+            match method {
+                "metashrew_height" => Ok(json!("100")),
+                "alkanes_simulate" => {
+                    self.requests.borrow_mut().push(params);
+                    Ok(json!({ "execution": self.executions.borrow_mut().remove(0) }))
+                }
+                _ => bail!("unexpected {}", method),
+            }
+        }
+    }
+
+    fn run_line(rpc: &MockRpc, line: &str) -> Result<Report> {
+        run(rpc, &Args::parse(line.split_whitespace().map(String::from))?)
+    }
+
+    fn words(values: &[u128]) -> String {
+        hex::encode(values.iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_views() {
+        let balances: Vec<u8> =
+            [U256::from(1_000u64), U256::from(2_000u64)].iter().flat_map(|b| b.to_le_bytes::<32>()).collect();
+        let rpc = MockRpc::new(vec![
+            json!({ "data": format!("0x{}", hex::encode(balances)), "alkanes": [] }),
+            json!({ "data": words(&[990, 1_000, 8, 2]) }),
+            json!({ "data": words(&[1_000_000_000_000_000_000, 990_000_000_000_000_000, 10_000_000_000_000_000]) }),
+        ]);
+        let report = run_line(&rpc, "balances --pool 2:21").unwrap();
+        assert_eq!(report.render(false), "balances: 1000, 2000");
+        assert_eq!(report.render(true), r#"{"balances":["1000","2000"]}"#);
+
+        let report = run_line(&rpc, "quote --pool 2:21 --from 0 --to 1 --dx 1000").unwrap();
+        assert_eq!(
+            report.render(false),
+            "dy: 990\ndy_gross: 1000\nlp_fee: 8\nadmin_fee: 2\nspot_price: 1\nexecution_price: 0.99\nprice_impact: 0.01"
+        );
+        assert_eq!(rpc.inputs(), vec![json!(["201"]), json!(["207", "0", "1", "1000"]), json!(["210", "0", "1", "1000"])]);
+        assert_eq!(rpc.requests.borrow()[1][0]["target"], json!({ "block": "2", "tx": "21" }));
+    }
+
+    #[test]
+    fn test_calls() {
+        let revert = format!("ALKANES: revert: {}", SynthPoolError::DeadlinePassed { deadline: 90, height: 100 });
+        let rpc = MockRpc::new(vec![
+            json!({ "data": "0x", "alkanes": [{ "id": { "block": "2", "tx": "3" }, "value": "995" }] }),
+            json!({ "data": "0x", "alkanes": [], "error": revert }),
+        ]);
+        let report = run_line(&rpc, "swap --pool 2:21 --sell 2:1:1000 --to 1 --min-dy 990").unwrap();
+        assert_eq!(report.render(false), "returned: 2:3:995");
+        let request = rpc.requests.borrow()[0][0].clone();
+        assert_eq!(request["inputs"], json!(["35", "1", "990", "0"]));
+        assert_eq!(request["alkanes"], json!([{ "id": { "block": "2", "tx": "1" }, "value": "1000" }]));
+
+        let err = run_line(&rpc, "remove-liquidity --pool 2:21 --lp 50 --one-coin 0 --min-amount 1 --deadline 90");
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "Pool reverted: DeadlinePassed { deadline: 90, height: 100 }"
+        );
+        assert_eq!(rpc.requests.borrow()[1][0]["alkanes"][0]["id"], json!({ "block": "2", "tx": "21" }));
+        assert!(run_line(&rpc, "swap --pool 2:21 --sell 2:1:1000 --to 1 --min-dy 990 --min-dx 1").is_err());
+    }
+
+    #[test]
+    fn test_psbt_despite_revert() {
+        let revert = format!("ALKANES: revert: {}", SynthPoolError::NotOwner {});
+        let rpc = MockRpc::new(vec![
+            json!({ "data": "0x", "alkanes": [], "error": revert }),
+            json!({ "data": "0x", "alkanes": [], "error": revert }),
+        ]);
+        let utxos = std::env::temp_dir().join(format!("slope-cli-utxos-{}.json", std::process::id()));
+        std::fs::write(
+            &utxos,
+            r#"[{"txid": "0101010101010101010101010101010101010101010101010101010101010101", "vout": 0,
+                 "value": 100000, "script_pubkey": "5120aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"}]"#,
+        )
+        .unwrap();
+        let line = format!(
+            "claim-admin-fees --pool 2:21 --utxos {} --recipient 5120bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+            utxos.display()
+        );
+        let report = run_line(&rpc, &line);
+        std::fs::remove_file(&utxos).unwrap();
+        let report = report.unwrap();
+        assert_eq!(report.0[0], ("warning", Field::Text("Pool reverted: NotOwner".to_string())));
+        assert_eq!(report.0[1].0, "psbt");
+
+        // without a wallet there is nothing to build, so the revert is an error
+        let err = run_line(&rpc, "claim-admin-fees --pool 2:21").unwrap_err();
+        assert_eq!(err.to_string(), "Pool reverted: NotOwner");
+    }
+
+    #[test]
+    fn test_init_pool() {
+        let rpc = MockRpc::new(vec![json!({ "data": "0x", "alkanes": [] })]);
+        let line = "init-pool --pool 2:21 --coins 2:1,2:3 --a 100 --fee 4000000 --admin-fee 0 --owner 2:5";
+        run_line(&rpc, &format!("{} --rate-oracles 0,104", line)).unwrap();
+        let expected = SynthPoolCall::InitPoolN {
+            coins: vec![2, 1, 2, 3],
+            A: 100,
+            fee: 4_000_000,
+            coin_fees: vec![],
+            decimals: vec![],
+            rate_oracles: vec![0, 104],
+            admin_fee: 0,
+            owner: AlkaneId::new(2, 5),
+        };
+        let inputs: Vec<String> = expected.inputs().iter().map(u128::to_string).collect();
+        assert_eq!(rpc.inputs(), vec![json!(inputs)]);
+
+        let err = run_line(&rpc, &format!("{} --rate-oracles 2:7", line)).unwrap_err();
+        assert!(err.to_string().contains("--rate-oracles"));
+        let err = run_line(&rpc, &format!("{} --rate-oracles 104", line)).unwrap_err();
+        assert_eq!(err.to_string(), "--rate-oracles takes one opcode per coin, 1 for 2 coins");
+        assert_eq!(rpc.inputs().len(), 1);
+    }
+}
@@ -0,0 +1,50 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+//! Deploys and operates SLOPE pools over an alkanes JSON-RPC endpoint.
+
+mod args;
+mod commands;
+mod rpc;
+mod tx;
+
+use anyhow::Result;
+
+use crate::args::Args;
+use crate::rpc::HttpRpc;
+
+const DEFAULT_RPC: &str = "http://localhost:18888";
+
+fn run() -> Result<()> {
+    let args = Args::parse(std::env::args().skip(1))?;
+    if args.help || args.command.is_empty() {
+        println!("{}", commands::USAGE);
+        return Ok(());
+    }
+    let url = match args.get("rpc") {
+        Some(url) => url.to_string(),
+        None => std::env::var("SLOPE_RPC").unwrap_or_else(|_| DEFAULT_RPC.to_string()),
+    };
+    let report = commands::run(&HttpRpc::new(&url), &args)?;
+    println!("{}", report.render(args.json));
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {:#}", err);
+        std::process::exit(1);
+    }
+}
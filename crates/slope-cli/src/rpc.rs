@@ -0,0 +1,145 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+//! The alkanes/metashrew JSON-RPC calls the tool makes: `metashrew_height`
+//! for the tip and `alkanes_simulate` to run a call against it.
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Value};
+use slope_sdk::{AlkaneId, SynthPoolCall, SynthPoolError};
+
+pub trait Rpc {
+    /// The `result` of a JSON-RPC request
+    fn call(&self, method: &str, params: Value) -> Result<Value>;
+}
+
+pub struct HttpRpc {
+    url: String,
+}
+
+impl HttpRpc {
+    pub fn new(url: &str) -> Self {
+        Self { url: url.to_string() }
+    }
+}
+
+impl Rpc for HttpRpc {
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let body = ureq::post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(&request.to_string())?
+            .into_string()?;
+        let mut response: Value = serde_json::from_str(&body)?;
+        if let Some(error) = response.get("error").filter(|error| !error.is_null()) {
+            bail!("{} failed: {}", method, error);
+        }
+        match response.get_mut("result") {
+            Some(result) => Ok(result.take()),
+            None => bail!("{} returned no result", method),
+        }
+    }
+}
+
+/// A u128 the endpoint may send as a number or a string
+fn number(value: &Value) -> Result<u128> {
+    match value {
+        Value::Number(number) => number.as_u64().map(u128::from),
+        Value::String(string) => string.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| anyhow!("{} is not an amount", value))
+}
+
+fn id_json(id: AlkaneId) -> Value {
+    json!({ "block": id.block.to_string(), "tx": id.tx.to_string() })
+}
+
+/// Height of the indexer's tip
+pub fn height(rpc: &dyn Rpc) -> Result<u128> {
+    number(&rpc.call("metashrew_height", json!([]))?)
+}
+
+/// Outcome of a simulated call
+#[derive(Debug, Default)]
+pub struct Simulation {
+    pub data: Vec<u8>,
+    /// Alkanes the call returns to the caller
+    pub alkanes: Vec<(AlkaneId, u128)>,
+    /// Revert message, if the call failed
+    pub error: Option<String>,
+}
+
+impl Simulation {
+    /// The response data, or the pool's error if the call reverted
+    pub fn into_data(self) -> Result<Vec<u8>> {
+        match self.error {
+            None => Ok(self.data),
+            Some(error) => match SynthPoolError::from_revert_data(error.as_bytes()) {
+                Some(error) => bail!("Pool reverted: {:?}", error),
+                None => bail!("{}", error),
+            },
+        }
+    }
+}
+
+/// Runs `call` on `pool` at `height`, sending it `alkanes`
+pub fn simulate(
+    rpc: &dyn Rpc,
+    pool: AlkaneId,
+    call: &SynthPoolCall,
+    alkanes: &[(AlkaneId, u128)],
+    height: u128,
+) -> Result<Simulation> {
+    let params = json!([{
+        "alkanes": alkanes
+            .iter()
+            .map(|&(id, value)| json!({ "id": id_json(id), "value": value.to_string() }))
+            .collect::<Vec<_>>(),
+        "transaction": "0x",
+        "block": "0x",
+        "height": height.to_string(),
+        "txindex": 0,
+        "target": id_json(pool),
+        "inputs": call.inputs().iter().map(u128::to_string).collect::<Vec<_>>(),
+        "pointer": 0,
+        "refundPointer": 0,
+        "vout": 0,
+    }]);
+    let result = rpc.call("alkanes_simulate", params)?;
+    let execution = &result["execution"];
+    let data = execution["data"].as_str().unwrap_or_default();
+    let alkanes = match execution["alkanes"].as_array() {
+        Some(transfers) => transfers
+            .iter()
+            .map(|transfer| {
+                let id = AlkaneId::new(number(&transfer["id"]["block"])?, number(&transfer["id"]["tx"])?);
+                Ok((id, number(&transfer["value"])?))
+            })
+            .collect::<Result<_>>()?,
+        None => vec![],
+    };
+    Ok(Simulation {
+        data: hex::decode(data.trim_start_matches("0x"))?,
+        alkanes,
+        error: execution["error"].as_str().filter(|error| !error.is_empty()).map(String::from),
+    })
+}
+
+/// Response data of the view `call`
+pub fn view(rpc: &dyn Rpc, pool: AlkaneId, call: &SynthPoolCall) -> Result<Vec<u8>> {
+    let height = height(rpc)?;
+    simulate(rpc, pool, call, &[], height)?.into_data()
+}
@@ -0,0 +1,134 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use bitcoin::{Amount, FeeRate, OutPoint, ScriptBuf, TxOut, Txid};
+use serde::Deserialize;
+use slope_sdk::psbt::{TxBuilder, Utxo};
+use slope_sdk::{AlkaneId, SynthPoolCall};
+
+use crate::args::{parse_id, Args};
+
+/// A wallet UTXO as listed in the `--utxos` file
+#[derive(Debug, Deserialize)]
+struct UtxoJson {
+    txid: String,
+    vout: u32,
+    /// Sats
+    value: u64,
+    /// Hex
+    script_pubkey: String,
+    #[serde(default)]
+    alkanes: Vec<AlkaneJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlkaneJson {
+    /// `block:tx`
+    id: String,
+    /// Decimal string, as u128 amounts overflow JSON numbers
+    amount: String,
+}
+
+fn parse_utxos(json: &str) -> Result<Vec<Utxo>> {
+    let utxos: Vec<UtxoJson> = serde_json::from_str(json)?;
+    utxos
+        .into_iter()
+        .map(|utxo| {
+            let alkanes = utxo
+                .alkanes
+                .iter()
+                .map(|alkane| Ok((parse_id(&alkane.id)?, alkane.amount.parse()?)))
+                .collect::<Result<_>>()?;
+            Ok(Utxo {
+                outpoint: OutPoint::new(Txid::from_str(&utxo.txid)?, utxo.vout),
+                txout: TxOut {
+                    value: Amount::from_sat(utxo.value),
+                    script_pubkey: ScriptBuf::from_hex(&utxo.script_pubkey)?,
+                },
+                alkanes,
+            })
+        })
+        .collect()
+}
+
+/// Where the PSBTs of calls spend from and pay to
+pub struct Wallet {
+    utxos: Vec<Utxo>,
+    recipient: ScriptBuf,
+    change: ScriptBuf,
+    fee_rate: FeeRate,
+}
+
+impl Wallet {
+    /// The wallet given by `--utxos`, if any
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        let Some(path) = args.get("utxos") else {
+            return Ok(None);
+        };
+        let utxos = std::fs::read_to_string(path).with_context(|| format!("Reading {}", path))?;
+        let recipient = ScriptBuf::from_hex(args.required("recipient")?)?;
+        let change = match args.get("change") {
+            Some(change) => ScriptBuf::from_hex(change)?,
+            None => recipient.clone(),
+        };
+        let fee_rate = args.u128("fee-rate")?.unwrap_or(2);
+        Ok(Some(Self {
+            utxos: parse_utxos(&utxos).with_context(|| format!("Parsing {}", path))?,
+            recipient,
+            change,
+            fee_rate: u64::try_from(fee_rate)
+                .ok()
+                .and_then(FeeRate::from_sat_per_vb)
+                .ok_or_else(|| anyhow!("Fee rate {} sat/vB is too high", fee_rate))?,
+        }))
+    }
+
+    /// Base64 PSBT making `call`
+    pub fn build_psbt(self, pool: AlkaneId, call: &SynthPoolCall, transfers: &[(AlkaneId, u128)]) -> Result<String> {
+        let builder = TxBuilder {
+            pool,
+            utxos: self.utxos,
+            recipient: self.recipient,
+            change: self.change,
+            fee_rate: self.fee_rate,
+        };
+        Ok(builder.build(call, transfers)?.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_utxos() {
+        let json = r#"[
+            {"txid": "0101010101010101010101010101010101010101010101010101010101010101", "vout": 1,
+             "value": 546, "script_pubkey": "5120aa", "alkanes": [{"id": "2:1", "amount": "600"}]},
+            {"txid": "0202020202020202020202020202020202020202020202020202020202020202", "vout": 0,
+             "value": 10000, "script_pubkey": "5120bb"}
+        ]"#;
+        let utxos = parse_utxos(json).unwrap();
+        assert_eq!(utxos.len(), 2);
+        assert_eq!(utxos[0].outpoint.vout, 1);
+        assert_eq!(utxos[0].alkanes, vec![(AlkaneId::new(2, 1), 600)]);
+        assert_eq!(utxos[1].txout.value, Amount::from_sat(10_000));
+        assert!(utxos[1].alkanes.is_empty());
+        assert!(parse_utxos(r#"[{"txid": "01", "vout": 0, "value": 1, "script_pubkey": ""}]"#).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+//! Errors raised by the synth-pool contract.
+//!
+//! Every error has a stable numeric code and a list of `u128` payload fields.
+//! The binary form is the code as u32 LE followed by each field as u128 LE.
+//! A contract failure only carries a string, so the `Display` form embeds
+//! that binary form as hex after [`ERROR_TAG`], followed by a readable message:
+//!
+//! `SYNTH_POOL_ERROR:f401000000...:Slippage: expected at least 1000, got 999`
+//!
+//! [`SynthPoolError::from_revert_data`] recovers the error from the revert
+//! data, whatever prefix the runtime puts in front of it.
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Marks the start of an encoded error in revert data
+pub const ERROR_TAG: &str = "SYNTH_POOL_ERROR:";
+
+macro_rules! synth_pool_errors {
+    ($($code:literal $name:ident { $($field:ident),* } => $message:literal,)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum SynthPoolError {
+            $($name { $($field: u128),* },)*
+        }
+
+        impl SynthPoolError {
+            /// Stable numeric code of the error
+            pub fn code(&self) -> u32 {
+                match self {
+                    $(Self::$name { .. } => $code,)*
+                }
+            }
+
+            /// Payload fields in declaration order
+            pub fn fields(&self) -> Vec<u128> {
+                match self {
+                    $(Self::$name { $($field),* } => vec![$(*$field),*],)*
+                }
+            }
+
+            /// Rebuilds an error from its code and payload fields
+            pub fn from_parts(code: u32, fields: &[u128]) -> Option<Self> {
+                match code {
+                    $($code => match fields {
+                        [$($field),*] => Some(Self::$name { $($field: *$field),* }),
+                        _ => None,
+                    },)*
+                    _ => None,
+                }
+            }
+
+            fn message(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    $(Self::$name { $($field),* } => {
+                        $(let _ = $field;)*
+                        write!(f, $message)
+                    })*
+                }
+            }
+        }
+    };
+}
+
+synth_pool_errors! {
+    // pool setup
+    100 AlreadyInitialized {} => "Pool already initialized",
+    101 InvalidCoinCount { count, max } => "Pool must hold between 2 and {max} coins, got {count}",
+    102 MalformedCoinList { len } => "Coins must be given as (block, tx) pairs, got {len} values",
+    103 CoinNotSet { index } => "Coin {index} is not set",
+    104 DuplicateCoin { index } => "Coin {index} is listed twice",
+    105 AOutOfRange { value } => "A out of range: {value}",
+    106 FeeTooHigh { fee, max } => "Fee exceeds maximum: {fee} > {max}",
+    107 AdminFeeTooHigh { admin_fee, max } => "Admin fee exceeds maximum: {admin_fee} > {max}",
+    108 OwnerNotSet {} => "Owner is not set",
+    109 OffpegFeeMultiplierTooHigh { offpeg_fee_multiplier, max } => "Off-peg fee multiplier exceeds maximum: {offpeg_fee_multiplier} > {max}",
+    110 DecimalsOutOfRange { index, decimals, max } => "Coin {index} decimals exceed maximum: {decimals} > {max}",
+
+    // access control
+    200 NoOwner {} => "Pool has no owner",
+    201 NotOwner {} => "Not the owner",
+    202 NotPendingOwner {} => "Not the pending owner",
+    203 Killed {} => "Pool is killed",
+    204 KillDeadlinePassed { deadline, height } => "Kill deadline has passed: height {height} >= {deadline}",
+
+    // governance
+    300 ActiveAction { deadline } => "Active action until height {deadline}",
+    301 NoActiveAction {} => "No active action",
+    302 ActionDelayNotPassed { deadline, height } => "Action delay has not passed: height {height} < {deadline}",
+    303 ActiveTransfer { deadline } => "Active transfer until height {deadline}",
+    304 NoActiveTransfer {} => "No active transfer",
+    305 TransferDelayNotPassed { deadline, height } => "Transfer delay has not passed: height {height} < {deadline}",
+    306 NewOwnerNotSet {} => "New owner is not set",
+    307 RampTooSoon { earliest, height } => "Cannot ramp A again before height {earliest}, now {height}",
+    308 RampTooShort { future_height, earliest } => "Ramp must end at height {earliest} or later, got {future_height}",
+    309 FutureHeightOutOfRange { future_height } => "future_height out of range: {future_height}",
+    310 FutureAOutOfRange { future_a } => "future_a out of range: {future_a}",
+    311 ADecreaseTooLarge { initial_a, future_a } => "A decrease too large: {initial_a} -> {future_a}",
+    312 AIncreaseTooLarge { initial_a, future_a } => "A increase too large: {initial_a} -> {future_a}",
+    313 OracleHalfLifeOutOfRange { half_life, max } => "Oracle half-life must be between 1 and {max} blocks, got {half_life}",
+
+    // call inputs
+    400 DeadlinePassed { deadline, height } => "Deadline passed: height {height} > {deadline}",
+    401 CoinIndexOutOfRange { index, n_coins } => "Coin index {index} out of range for {n_coins} coins",
+    402 SameCoin { index } => "Cannot swap coin {index} for itself",
+    403 MixedInputCoins {} => "Cannot swap more than one coin at a time",
+    404 NoInputCoin {} => "No coin to swap provided in transaction",
+    405 NoLpTokens {} => "No LP tokens to burn in incoming transaction",
+    406 WrongAmountCount { expected, actual } => "Expected {expected} amounts, got {actual}",
+    407 ZeroAmount {} => "Amount must be positive",
+    408 InvalidTokenAmount { amount, total_supply } => "Invalid token amount {amount} for supply {total_supply}",
+    409 InsufficientInput { required, provided } => "Not enough coins sent: need {required}, got {provided}",
+    410 InsufficientLpTokens { required, provided } => "Not enough LP tokens sent: need {required}, got {provided}",
+    411 TwapWindowUnavailable { window_blocks, max_window } => "TWAP window must be between 1 and {max_window} blocks, got {window_blocks}",
+    412 PoolTooLarge { total, max } => "Pool balances would total {total}, above the maximum {max}",
+
+    // slippage
+    500 OutputBelowMinimum { minimum, actual } => "Slippage: expected at least {minimum}, got {actual}",
+    501 InputAboveMaximum { maximum, actual } => "Slippage: expected at most {maximum}, got {actual}",
+
+    // liquidity and invariant maths
+    600 InsufficientLiquidity { requested, available } => "Not enough liquidity: requested {requested}, available {available}",
+    601 EmptyPool {} => "Pool has no liquidity",
+    602 DNotIncreasing {} => "D1 must be greater than D0",
+    603 InsufficientSupply { amount, total_supply } => "Insufficient supply: burning {amount} of {total_supply}",
+    604 DDidNotConverge {} => "D does not converge",
+    605 YDidNotConverge {} => "y does not converge",
+    606 MathOverflow {} => "Arithmetic overflow",
+    607 MathUnderflow {} => "Arithmetic underflow",
+    608 DivisionByZero {} => "Division by zero",
+
+    // cross-contract calls
+    700 RateUnavailable { index } => "Rate oracle of coin {index} returned no rate",
+}
+
+impl SynthPoolError {
+    /// Binary form: code as u32 LE, then each payload field as u128 LE
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = self.code().to_le_bytes().to_vec();
+        for field in self.fields() {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data
+    }
+
+    /// Inverse of [`SynthPoolError::encode`]
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 4 {
+            return None;
+        }
+        let code = u32::from_le_bytes(data[..4].try_into().ok()?);
+        let chunks = data[4..].chunks_exact(16);
+        if !chunks.remainder().is_empty() {
+            return None;
+        }
+        let fields: Vec<u128> = chunks
+            .map(|chunk| u128::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        Self::from_parts(code, &fields)
+    }
+
+    /// Finds an encoded error anywhere in the revert data of a failed call
+    pub fn from_revert_data(data: &[u8]) -> Option<Self> {
+        let tag = ERROR_TAG.as_bytes();
+        let start = data.windows(tag.len()).position(|window| window == tag)? + tag.len();
+        let rest = &data[start..];
+        let end = rest.iter().position(|&b| b == b':').unwrap_or(rest.len());
+        Self::decode(&decode_hex(&rest[..end])?)
+    }
+}
+
+impl fmt::Display for SynthPoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(ERROR_TAG)?;
+        for byte in self.encode() {
+            write!(f, "{:02x}", byte)?;
+        }
+        f.write_str(":")?;
+        self.message(f)
+    }
+}
+
+impl core::error::Error for SynthPoolError {}
+
+fn decode_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    let pairs = hex.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    pairs
+        .map(|pair| u8::from_str_radix(core::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let errors = [
+            SynthPoolError::AlreadyInitialized {},
+            SynthPoolError::OutputBelowMinimum { minimum: 1000, actual: 999 },
+            SynthPoolError::DeadlinePassed { deadline: 10, height: u128::MAX },
+        ];
+        for error in errors {
+            assert_eq!(SynthPoolError::decode(&error.encode()), Some(error));
+            assert_eq!(SynthPoolError::from_parts(error.code(), &error.fields()), Some(error));
+        }
+    }
+
+    #[test]
+    fn test_from_revert_data() {
+        let error = SynthPoolError::OutputBelowMinimum { minimum: 1000, actual: 999 };
+        let message = error.to_string();
+        assert!(message.ends_with(":Slippage: expected at least 1000, got 999"));
+
+        // the runtime prefixes an error selector and "Error: " to the message
+        let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+        data.extend_from_slice(format!("Error: {}", message).as_bytes());
+        assert_eq!(SynthPoolError::from_revert_data(&data), Some(error));
+
+        assert_eq!(SynthPoolError::from_revert_data(b"Error: Slippage screwed you"), None);
+        assert_eq!(SynthPoolError::decode(&[0xf4, 0x01, 0, 0]), None);
+    }
+}
@@ -0,0 +1,255 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+//! Events emitted by the synth-pool contract.
+//!
+//! Every state-changing call returns exactly one event as its response data,
+//! so indexers can pick it up from the return context in the call's trace.
+//! Layout: version (u8), event kind (u8), then each field in declaration
+//! order. A `u128` field is 16 bytes LE. A `Vec<u128>` field is its length
+//! as u128 LE followed by the items. Per-coin vectors are indexed like the
+//! pool's coins. Alkane ids are split into `_block` and `_tx` fields.
+
+/// Bumped whenever a released layout of an existing event changes
+pub const EVENT_VERSION: u8 = 1;
+
+trait EventField: Sized {
+    fn encode(&self, data: &mut Vec<u8>);
+    fn decode(data: &mut &[u8]) -> Option<Self>;
+}
+
+impl EventField for u128 {
+    fn encode(&self, data: &mut Vec<u8>) {
+        data.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        if data.len() < 16 {
+            return None;
+        }
+        let (value, rest) = data.split_at(16);
+        *data = rest;
+        Some(u128::from_le_bytes(value.try_into().unwrap()))
+    }
+}
+
+impl EventField for Vec<u128> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        (self.len() as u128).encode(data);
+        for value in self {
+            value.encode(data);
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let len = usize::try_from(u128::decode(data)?).ok()?;
+        if len > data.len() / 16 {
+            return None;
+        }
+        (0..len).map(|_| u128::decode(data)).collect()
+    }
+}
+
+macro_rules! synth_pool_events {
+    ($($kind:literal $name:ident { $($field:ident: $ty:ty),* },)*) => {
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub enum SynthPoolEvent {
+            $($name { $($field: $ty),* },)*
+        }
+
+        impl SynthPoolEvent {
+            /// Stable numeric kind of the event
+            pub fn kind(&self) -> u8 {
+                match self {
+                    $(Self::$name { .. } => $kind,)*
+                }
+            }
+
+            pub fn encode(&self) -> Vec<u8> {
+                let mut data = vec![EVENT_VERSION, self.kind()];
+                match self {
+                    $(Self::$name { $($field),* } => {
+                        $(EventField::encode($field, &mut data);)*
+                    })*
+                }
+                data
+            }
+
+            /// Inverse of [`SynthPoolEvent::encode`]; `None` for unknown
+            /// versions or kinds and for malformed data
+            pub fn decode(data: &[u8]) -> Option<Self> {
+                let (&version, data) = data.split_first()?;
+                if version != EVENT_VERSION {
+                    return None;
+                }
+                let (&kind, mut data) = data.split_first()?;
+                let event = match kind {
+                    $($kind => Self::$name {
+                        $($field: <$ty as EventField>::decode(&mut data)?),*
+                    },)*
+                    _ => return None,
+                };
+                if !data.is_empty() {
+                    return None;
+                }
+                Some(event)
+            }
+        }
+    };
+}
+
+synth_pool_events! {
+    1 InitPool {
+        coins: Vec<u128>,
+        a: u128,
+        fee: u128,
+        coin_fees: Vec<u128>,
+        decimals: Vec<u128>,
+        rate_oracles: Vec<u128>,
+        admin_fee: u128,
+        owner_block: u128,
+        owner_tx: u128
+    },
+    // fee and admin_fee are taken from the bought coin
+    2 TokenExchange {
+        sold_id: u128,
+        tokens_sold: u128,
+        bought_id: u128,
+        tokens_bought: u128,
+        fee: u128,
+        admin_fee: u128,
+        balances: Vec<u128>,
+        token_supply: u128
+    },
+    3 AddLiquidity {
+        token_amounts: Vec<u128>,
+        fees: Vec<u128>,
+        minted: u128,
+        balances: Vec<u128>,
+        token_supply: u128
+    },
+    4 RemoveLiquidity {
+        token_amounts: Vec<u128>,
+        burned: u128,
+        balances: Vec<u128>,
+        token_supply: u128
+    },
+    5 RemoveLiquidityOne {
+        coin_index: u128,
+        coin_amount: u128,
+        fee: u128,
+        burned: u128,
+        balances: Vec<u128>,
+        token_supply: u128
+    },
+    6 RemoveLiquidityImbalance {
+        token_amounts: Vec<u128>,
+        fees: Vec<u128>,
+        burned: u128,
+        balances: Vec<u128>,
+        token_supply: u128
+    },
+    7 ClaimAdminFees {
+        amounts: Vec<u128>
+    },
+    8 RampA {
+        old_a: u128,
+        new_a: u128,
+        initial_height: u128,
+        future_height: u128
+    },
+    9 StopRampA {
+        a: u128,
+        height: u128
+    },
+    10 CommitNewFee {
+        deadline: u128,
+        fee: u128,
+        admin_fee: u128,
+        coin_fees: Vec<u128>,
+        offpeg_fee_multiplier: u128
+    },
+    11 NewFee {
+        fee: u128,
+        admin_fee: u128,
+        coin_fees: Vec<u128>,
+        offpeg_fee_multiplier: u128
+    },
+    12 RevertNewParameters {},
+    13 CommitTransferOwnership {
+        deadline: u128,
+        new_owner_block: u128,
+        new_owner_tx: u128
+    },
+    14 TransferOwnership {
+        owner_block: u128,
+        owner_tx: u128
+    },
+    15 RevertTransferOwnership {},
+    16 RenounceOwnership {},
+    17 Kill {},
+    18 Unkill {},
+    19 SetOracleHalfLife {
+        half_life: u128
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let events = [
+            SynthPoolEvent::TokenExchange {
+                sold_id: 0,
+                tokens_sold: 1_000,
+                bought_id: 1,
+                tokens_bought: 996,
+                fee: 4,
+                admin_fee: 2,
+                balances: vec![1_001_000, 999_004],
+                token_supply: 2_000_000,
+            },
+            SynthPoolEvent::ClaimAdminFees { amounts: vec![] },
+            SynthPoolEvent::Kill {},
+        ];
+        for event in events {
+            let data = event.encode();
+            assert_eq!(data[..2], [EVENT_VERSION, event.kind()]);
+            assert_eq!(SynthPoolEvent::decode(&data), Some(event));
+        }
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_data() {
+        let data = SynthPoolEvent::NewFee {
+            fee: 1,
+            admin_fee: 2,
+            coin_fees: vec![1, 1],
+            offpeg_fee_multiplier: 0,
+        }
+        .encode();
+        assert_eq!(SynthPoolEvent::decode(&data[..data.len() - 1]), None);
+        assert_eq!(SynthPoolEvent::decode(&[data.as_slice(), &[0]].concat()), None);
+        assert_eq!(SynthPoolEvent::decode(&[EVENT_VERSION + 1, 11]), None);
+        assert_eq!(SynthPoolEvent::decode(&[EVENT_VERSION, 0]), None);
+
+        // a vector length larger than the remaining data
+        let mut data = vec![EVENT_VERSION, 7];
+        data.extend_from_slice(&u128::MAX.to_le_bytes());
+        assert_eq!(SynthPoolEvent::decode(&data), None);
+    }
+}
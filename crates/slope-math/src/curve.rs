@@ -0,0 +1,257 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+use alloc::vec;
+use alloc::vec::Vec;
+use ruint::aliases::U256;
+use slope_errors::SynthPoolError;
+
+use crate::{Result, TryMath, A_PRECISION, FEE_DENOMINATOR, PRECISION};
+
+const LN_2: U256 = U256::from_limbs([693_147_180_559_945_309, 0, 0, 0]);
+
+/// D invariant calculation in non-overflowing integer operations
+/// iteratively
+///
+/// A * sum(x_i) * n**n + D = A * D * n**n + D**(n+1) / (n**n * prod(x_i))
+///
+/// Converging solution:
+/// D[j+1] = (A * n**n * sum(x_i) - D[j]**(n+1) / (n**n prod(x_i))) / (A * n**n - 1)
+pub fn get_D(xp: &[U256], amp: U256) -> Result<U256> {
+    let n_coins = U256::from(xp.len());
+    let mut S = U256::ZERO;
+    for &x in xp.iter() {
+        S = S.try_add(x)?;
+    }
+    if S == U256::ZERO {
+        return Ok(U256::ZERO);
+    }
+
+    let mut Dprev;
+    let mut D = S;
+    let Ann = amp.try_mul(n_coins)?;
+
+    for _i in 0..255 {
+        let mut D_P = D;
+        for &x in xp.iter() {
+            // D_P = D_P * D / (x * N_COINS + 1)
+            D_P = D_P.try_mul(D)?.try_div(x.try_mul(n_coins)?.try_add(U256::from(1))?)?;
+        }
+        Dprev = D;
+        // D = (Ann * S / A_PRECISION + D_P * N_COINS) * D / ((Ann - A_PRECISION) * D / A_PRECISION + (N_COINS + 1) * D_P)
+        let num = (Ann.try_mul(S)? / A_PRECISION).try_add(D_P.try_mul(n_coins)?)?.try_mul(D)?;
+        let den = (Ann.try_sub(A_PRECISION)?.try_mul(D)? / A_PRECISION)
+            .try_add((n_coins + U256::from(1)).try_mul(D_P)?)?;
+        D = num.try_div(den)?;
+        if D > Dprev {
+            if D - Dprev <= U256::from(1) {
+                return Ok(D);
+            }
+        } else {
+            if Dprev - D <= U256::from(1) {
+                return Ok(D);
+            }
+        }
+    }
+    Err(SynthPoolError::DDidNotConverge {})
+}
+
+/// Calculate x[j] if one makes x[i] = x
+///
+/// Done by solving quadratic equation iteratively.
+/// x_1**2 + x1 * (sum' - (A*n**n - 1) * D / (A * n**n)) = D ** (n + 1) / (n ** (2 * n) * prod' * A)
+/// x_1**2 + b*x_1 = c
+///
+/// x_1 = (x_1**2 + c) / (2*x_1 + b)
+pub fn get_y(i: usize, j: usize, x: U256, xp: &[U256], amp: U256, D: U256) -> Result<U256> {
+    // x in the input is converted to the same price/precision
+    check_index(i, xp.len())?;
+    check_index(j, xp.len())?;
+    if i == j {
+        return Err(SynthPoolError::SameCoin { index: i as u128 });
+    }
+
+    let n_coins = U256::from(xp.len());
+    let Ann = amp.try_mul(n_coins)?;
+    let mut c = D;
+    let mut S_ = U256::ZERO;
+    let mut _x;
+
+    for (_i, &xp_i) in xp.iter().enumerate() {
+        if _i == i {
+            _x = x;
+        } else if _i != j {
+            _x = xp_i;
+        } else {
+            continue;
+        }
+        S_ = S_.try_add(_x)?;
+        // c = c * D / (_x * N_COINS)
+        c = c.try_mul(D)?.try_div(_x.try_mul(n_coins)?)?;
+    }
+    // c = c * D * A_PRECISION / (Ann * N_COINS)
+    c = c.try_mul(D)?.try_mul(A_PRECISION)?.try_div(Ann.try_mul(n_coins)?)?;
+    // b = S_ + D * A_PRECISION / Ann
+    let b = S_.try_add(D.try_mul(A_PRECISION)?.try_div(Ann)?)?;
+    let mut y_prev;
+    let mut y = D;
+
+    for _i in 0..255 {
+        y_prev = y;
+        // y = (y*y + c) / (2 * y + b - D)
+        y = y.try_mul(y)?.try_add(c)?.try_div(U256::from(2).try_mul(y)?.try_add(b)?.try_sub(D)?)?;
+        if y > y_prev {
+            if y - y_prev <= U256::from(1) {
+                return Ok(y);
+            }
+        } else {
+            if y_prev - y <= U256::from(1) {
+                return Ok(y);
+            }
+        }
+    }
+
+    Err(SynthPoolError::YDidNotConverge {})
+}
+
+/// Calculate x[i] if one reduces D from being calculated for xp to D
+///
+/// Done by solving quadratic equation iteratively.
+/// x_1**2 + x1 * (sum' - (A*n**n - 1) * D / (A * n**n)) = D ** (n + 1) / (n ** (2 * n) * prod' * A)
+/// x_1**2 + b*x_1 = c
+///
+/// x_1 = (x_1**2 + c) / (2*x_1 + b)
+pub fn get_y_D(A: U256, i: usize, xp: &[U256], D: U256) -> Result<U256> {
+    check_index(i, xp.len())?;
+
+    let n_coins = U256::from(xp.len());
+    let Ann = A.try_mul(n_coins)?;
+    let mut c = D;
+    let mut S_ = U256::ZERO;
+    let mut _x;
+
+    for (_i, &xp_i) in xp.iter().enumerate() {
+        if _i != i {
+            _x = xp_i;
+        } else {
+            continue;
+        }
+        S_ = S_.try_add(_x)?;
+        c = c.try_mul(D)?.try_div(_x.try_mul(n_coins)?)?;
+    }
+    c = c.try_mul(D)?.try_mul(A_PRECISION)?.try_div(Ann.try_mul(n_coins)?)?;
+    let b = S_.try_add(D.try_mul(A_PRECISION)?.try_div(Ann)?)?;
+    let mut y_prev;
+    let mut y = D;
+
+    for _i in 0..255 {
+        y_prev = y;
+        y = y.try_mul(y)?.try_add(c)?.try_div(U256::from(2).try_mul(y)?.try_add(b)?.try_sub(D)?)?;
+        if y > y_prev {
+            if y - y_prev <= U256::from(1) {
+                return Ok(y);
+            }
+        } else {
+            if y_prev - y <= U256::from(1) {
+                return Ok(y);
+            }
+        }
+    }
+
+    Err(SynthPoolError::YDidNotConverge {})
+}
+
+pub(crate) fn check_index(index: usize, n_coins: usize) -> Result<()> {
+    if index >= n_coins {
+        return Err(SynthPoolError::CoinIndexOutOfRange { index: index as u128, n_coins: n_coins as u128 });
+    }
+    Ok(())
+}
+
+/// Spot prices of every coin in units of coin 0, scaled by 1e18
+///
+/// p[i] = dx_0 / dx_i = (Ann * x_0 / A_PRECISION + Dr * x_0 / x_i) / (Ann * x_0 / A_PRECISION + Dr)
+/// where Dr = D**(n+1) / (n**n * prod(x_i)); p[0] is always 1e18
+pub fn get_p(xp: &[U256], amp: U256, D: U256) -> Result<Vec<U256>> {
+    let n_coins = U256::from(xp.len());
+    let Ann = amp.try_mul(n_coins)?;
+    let mut Dr = D / n_coins.pow(n_coins);
+    for &x in xp.iter() {
+        if x == U256::ZERO {
+            return Err(SynthPoolError::EmptyPool {});
+        }
+        Dr = Dr.try_mul(D)? / x;
+    }
+    let xp0_A = Ann.try_mul(xp[0])? / A_PRECISION;
+    let mut p = vec![PRECISION];
+    for &x in xp.iter().skip(1) {
+        let num = xp0_A.try_add(Dr.try_mul(xp[0])? / x)?;
+        p.push(PRECISION.try_mul(num)?.try_div(xp0_A.try_add(Dr)?)?);
+    }
+    Ok(p)
+}
+
+/// Weight an EMA with the given half-life keeps on its old value after
+/// `elapsed` blocks, scaled by 1e18
+///
+/// alpha = 2**(-elapsed / half_life) = 2**(-q) * exp(-r * ln 2 / half_life)
+/// where elapsed = q * half_life + r
+pub fn half_life_decay(elapsed: u64, half_life: u64) -> Result<U256> {
+    let halvings = elapsed.checked_div(half_life).ok_or(SynthPoolError::DivisionByZero {})?;
+    if halvings >= 60 {
+        // 1e18 >> 60 is already zero
+        return Ok(U256::ZERO);
+    }
+    let x = U256::from(elapsed % half_life).try_mul(LN_2)? / U256::from(half_life);
+    Ok(exp_neg(x)? >> halvings as usize)
+}
+
+/// exp(-x) for 0 <= x < 1e18 (i.e. below 1.0), scaled by 1e18, from its
+/// Taylor series
+fn exp_neg(x: U256) -> Result<U256> {
+    let mut term = PRECISION;
+    let mut positive = PRECISION;
+    let mut negative = U256::ZERO;
+    for k in 1..40u64 {
+        term = term.try_mul(x)? / PRECISION.try_mul(U256::from(k))?;
+        if term == U256::ZERO {
+            break;
+        }
+        if k % 2 == 1 {
+            negative = negative.try_add(term)?;
+        } else {
+            positive = positive.try_add(term)?;
+        }
+    }
+    positive.try_sub(negative)
+}
+
+/// Fee scaled up as two balances drift apart (StableSwap-NG)
+///
+/// fee * m / ((m - 1) * 4 * xpi * xpj / (xpi + xpj)**2 + 1)
+/// with m = offpeg_fee_multiplier / FEE_DENOMINATOR; balanced coins pay
+/// `fee`, and the fee tends to m * fee as one side runs out
+pub fn dynamic_fee(xpi: U256, xpj: U256, fee: U256, offpeg_fee_multiplier: U256) -> Result<U256> {
+    let xps = xpi.try_add(xpj)?;
+    if offpeg_fee_multiplier <= FEE_DENOMINATOR || xps == U256::ZERO {
+        return Ok(fee);
+    }
+    let balance = (offpeg_fee_multiplier - FEE_DENOMINATOR)
+        .try_mul(U256::from(4))?
+        .try_mul(xpi)?
+        .try_mul(xpj)?
+        .try_div(xps.try_mul(xps)?)?;
+    offpeg_fee_multiplier.try_mul(fee)?.try_div(balance.try_add(FEE_DENOMINATOR)?)
+}
@@ -0,0 +1,92 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+//! StableSwap maths of the synth-pool contract.
+//!
+//! The contract prices every swap, deposit and withdrawal through this crate,
+//! so frontends, bots and indexers that use it get the same results as the
+//! chain, down to rounding. [`PoolState`] holds everything a quote reads and
+//! round-trips through the contract's GetPoolState view.
+//!
+//! All values are `U256`. Balances are in each coin's own units; the curve
+//! works on `xp`, the balances scaled by their rates into common 8-decimal
+//! units. Failures are the contract's own [`SynthPoolError`]s.
+#![cfg_attr(not(test), no_std)]
+#![allow(non_snake_case)]
+
+extern crate alloc;
+
+macro_rules! ensure {
+    ($cond:expr, $err:expr) => {
+        if !$cond {
+            return Err($err);
+        }
+    };
+}
+
+mod curve;
+mod pool;
+
+use ruint::aliases::U256;
+pub use slope_errors::SynthPoolError;
+
+pub use curve::{dynamic_fee, get_D, get_p, get_y, get_y_D, half_life_decay};
+pub use pool::{check_pool_size, xp_mem, PoolState};
+
+pub const A_PRECISION: U256 = U256::from_limbs([100, 0, 0, 0]);
+pub const PRECISION: U256 = U256::from_limbs([1_000_000_000_000_000_000, 0, 0, 0]);
+pub const FEE_DENOMINATOR: U256 = U256::from_limbs([10_000_000_000, 0, 0, 0]);
+/// Keeps get_D's largest Newton intermediate, D_P * n_coins * D, within
+/// U256 for pools of up to 8 coins that are not far out of balance. D_P
+/// grows as D**(n+1) / prod(n * x_i), so a pool with coins all but emptied
+/// can still exceed it; the maths then fails with MathOverflow.
+pub const MAX_XP_SUM: u128 = 10u128.pow(24);
+
+pub type Result<T> = core::result::Result<T, SynthPoolError>;
+
+/// Arithmetic that fails with a typed error where the plain operators
+/// would panic, wrap or saturate
+pub trait TryMath: Sized {
+    fn try_add(self, rhs: Self) -> Result<Self>;
+    fn try_sub(self, rhs: Self) -> Result<Self>;
+    fn try_mul(self, rhs: Self) -> Result<Self>;
+    fn try_div(self, rhs: Self) -> Result<Self>;
+}
+
+macro_rules! impl_try_math {
+    ($($t:ty),*) => {
+        $(impl TryMath for $t {
+            fn try_add(self, rhs: Self) -> Result<Self> {
+                self.checked_add(rhs).ok_or(SynthPoolError::MathOverflow {})
+            }
+            fn try_sub(self, rhs: Self) -> Result<Self> {
+                self.checked_sub(rhs).ok_or(SynthPoolError::MathUnderflow {})
+            }
+            fn try_mul(self, rhs: Self) -> Result<Self> {
+                self.checked_mul(rhs).ok_or(SynthPoolError::MathOverflow {})
+            }
+            fn try_div(self, rhs: Self) -> Result<Self> {
+                self.checked_div(rhs).ok_or(SynthPoolError::DivisionByZero {})
+            }
+        })*
+    };
+}
+
+impl_try_math!(U256, u128);
+
+/// Narrows a result to the u128 amounts alkanes carry
+pub fn to_u128(value: U256) -> Result<u128> {
+    u128::try_from(value).map_err(|_| SynthPoolError::MathOverflow {})
+}
@@ -0,0 +1,403 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+use alloc::vec;
+use alloc::vec::Vec;
+use ruint::aliases::U256;
+use slope_errors::SynthPoolError;
+
+use crate::curve::{check_index, dynamic_fee, get_D, get_p, get_y, get_y_D};
+use crate::{Result, TryMath, FEE_DENOMINATOR, MAX_XP_SUM, PRECISION};
+
+/// Everything a pool's pricing reads. The contract snapshots its storage
+/// into one for every quote and trade; clients decode one from the
+/// GetPoolState view, so their quotes match the contract's to the unit.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolState {
+    /// Balance of each coin in its own units owed to LPs, excluding the admin
+    /// fees awaiting a claim
+    pub balances: Vec<U256>,
+    /// Rate of each coin scaled by 1e18, mapping its balance into curve units
+    pub rates: Vec<U256>,
+    /// Current amplification coefficient, in A_PRECISION units
+    pub amp: U256,
+    /// Base fee, charged on imbalanced deposits and withdrawals
+    pub fee: U256,
+    /// Swap fee by output coin
+    pub coin_fees: Vec<U256>,
+    /// Share of every fee kept for the admin
+    pub admin_fee: U256,
+    pub offpeg_fee_multiplier: U256,
+    /// LP token supply
+    pub total_supply: U256,
+}
+
+/// Balances in the curve's common units
+pub fn xp_mem(rates: &[U256], balances: &[U256]) -> Result<Vec<U256>> {
+    rates
+        .iter()
+        .zip(balances)
+        .map(|(&rate, &balance)| Ok(rate.try_mul(balance)? / PRECISION))
+        .collect()
+}
+
+/// Deposits and swaps may not grow the pool past MAX_XP_SUM, the size its
+/// maths is sized for
+pub fn check_pool_size(xp: &[U256]) -> Result<()> {
+    let total = xp.iter().try_fold(U256::ZERO, |total, &x| total.try_add(x))?;
+    ensure!(
+        total <= U256::from(MAX_XP_SUM),
+        SynthPoolError::PoolTooLarge { total: total.saturating_to(), max: MAX_XP_SUM }
+    );
+    Ok(())
+}
+
+impl PoolState {
+    pub fn n_coins(&self) -> usize {
+        self.balances.len()
+    }
+
+    /// Balances in the curve's common units
+    pub fn xp(&self) -> Result<Vec<U256>> {
+        xp_mem(&self.rates, &self.balances)
+    }
+
+    fn check_pair(&self, i: usize, j: usize) -> Result<()> {
+        check_index(i, self.n_coins())?;
+        check_index(j, self.n_coins())?;
+        ensure!(i != j, SynthPoolError::SameCoin { index: i as u128 });
+        Ok(())
+    }
+
+    /// `fee` scaled by the off-peg multiplier for balances `xpi` and `xpj`
+    fn scaled_fee(&self, xpi: U256, xpj: U256, fee: U256) -> Result<U256> {
+        dynamic_fee(xpi, xpj, fee, self.offpeg_fee_multiplier)
+    }
+
+    /// Swap fee charged right now for selling coin `i` for coin `j`
+    pub fn swap_fee(&self, i: usize, j: usize) -> Result<U256> {
+        self.check_pair(i, j)?;
+        let xp = self.xp()?;
+        self.scaled_fee(xp[i], xp[j], self.coin_fees[j])
+    }
+
+    /// Output of swapping `dx` of coin `i` for coin `j`, as (dy after fees,
+    /// total fee, admin share of the fee).
+    pub fn get_dy(&self, i: usize, j: usize, dx: U256) -> Result<(U256, U256, U256)> {
+        self.check_pair(i, j)?;
+        let (rates, xp) = (&self.rates, self.xp()?);
+        let x = xp[i].try_add(dx.try_mul(rates[i])? / PRECISION)?;
+        let mut xp_after = xp.clone();
+        xp_after[i] = x;
+        check_pool_size(&xp_after)?;
+        let D = get_D(&xp, self.amp)?;
+        let y = get_y(i, j, x, &xp, self.amp, D)?;
+
+        // rounding in get_y can leave y a unit above xp[j] for a dust input
+        let dy = xp[j].saturating_sub(y).try_mul(PRECISION)?.try_div(rates[j])?;
+        let fee = self.scaled_fee(xp[i].try_add(x)? / U256::from(2), xp[j].try_add(y)? / U256::from(2), self.coin_fees[j])?;
+        let dy_fee = dy.try_mul(fee)? / FEE_DENOMINATOR;
+        let dy = dy.try_sub(dy_fee)?;
+
+        let dy_admin_fee = dy_fee.try_mul(self.admin_fee)? / FEE_DENOMINATOR;
+        Ok((dy, dy_fee, dy_admin_fee))
+    }
+
+    /// Input of coin `i` needed to receive exactly `dy` of coin `j` after fees.
+    pub fn get_dx(&self, i: usize, j: usize, dy: U256) -> Result<U256> {
+        self.check_pair(i, j)?;
+        let (rates, xp) = (&self.rates, self.xp()?);
+        let D = get_D(&xp, self.amp)?;
+
+        let base_fee = self.coin_fees[j];
+        // the off-peg fee depends on where the trade ends; start from the base
+        // fee, which is its floor, and raise it until it settles
+        let mut fee = base_fee;
+        for _ in 0..32 {
+            // gross up dy by the fee, rounding against the caller
+            let net = FEE_DENOMINATOR.try_sub(fee)?;
+            let dy_gross = dy.try_mul(FEE_DENOMINATOR)?.try_add(net.try_sub(U256::from(1))?)?.try_div(net)?;
+            let dy_gross_xp = dy_gross.try_mul(rates[j])?.try_add(PRECISION - U256::from(1))? / PRECISION;
+            ensure!(
+                dy_gross_xp < xp[j],
+                SynthPoolError::InsufficientLiquidity {
+                    requested: dy_gross.saturating_to(),
+                    available: self.balances[j].saturating_to(),
+                }
+            );
+            let y = xp[j].try_sub(dy_gross_xp)?;
+            let x = get_y(j, i, y, &xp, self.amp, D)?;
+            let dx = x
+                .try_sub(xp[i])?
+                .try_mul(PRECISION)?
+                .try_add(rates[i].try_sub(U256::from(1))?)?
+                .try_div(rates[i])?
+                .try_add(U256::from(1))?;
+            // the fee get_dy will charge on this dx
+            let x = xp[i].try_add(dx.try_mul(rates[i])? / PRECISION)?;
+            let next_fee = self.scaled_fee(xp[i].try_add(x)? / U256::from(2), xp[j].try_add(y)? / U256::from(2), base_fee)?;
+            if next_fee <= fee {
+                return Ok(dx);
+            }
+            fee = next_fee;
+        }
+        Err(SynthPoolError::YDidNotConverge {})
+    }
+
+    /// Spot prices of every coin in units of coin 0 in curve units, i.e.
+    /// net of rates, scaled by 1e18
+    pub fn prices(&self) -> Result<Vec<U256>> {
+        let xp = self.xp()?;
+        let D = get_D(&xp, self.amp)?;
+        get_p(&xp, self.amp, D)
+    }
+
+    /// Marginal price of coin `i` in units of coin `j`, scaled by 1e18
+    pub fn spot_price(&self, i: usize, j: usize) -> Result<U256> {
+        self.check_pair(i, j)?;
+        let p = self.prices()?;
+        p[i].try_mul(PRECISION)?.try_div(p[j])?.try_mul(self.rates[i])?.try_div(self.rates[j])
+    }
+
+    /// LP tokens minted by depositing (or burned by withdrawing) `amounts`,
+    /// along with the per-coin imbalance fees when `with_fees` is set.
+    pub fn calc_token_amount(&self, amounts: &[U256], is_deposit: bool, with_fees: bool) -> Result<(U256, Vec<U256>)> {
+        let n_coins = self.n_coins();
+        ensure!(
+            amounts.len() == n_coins,
+            SynthPoolError::WrongAmountCount { expected: n_coins as u128, actual: amounts.len() as u128 }
+        );
+        let (amp, rates, old_balances) = (self.amp, &self.rates, &self.balances);
+        let token_supply = self.total_supply;
+        let mut fees = vec![U256::ZERO; n_coins];
+        let D0 = if token_supply > U256::ZERO {
+            get_D(&xp_mem(rates, old_balances)?, amp)?
+        } else {
+            U256::ZERO
+        };
+
+        let mut new_balances = old_balances.clone();
+        for i in 0..n_coins {
+            if is_deposit {
+                new_balances[i] = new_balances[i].try_add(amounts[i])?;
+            } else {
+                ensure!(
+                    amounts[i] <= old_balances[i],
+                    SynthPoolError::InsufficientLiquidity {
+                        requested: amounts[i].saturating_to(),
+                        available: old_balances[i].saturating_to(),
+                    }
+                );
+                new_balances[i] = new_balances[i].try_sub(amounts[i])?;
+            }
+        }
+
+        let new_xp = xp_mem(rates, &new_balances)?;
+        if is_deposit {
+            check_pool_size(&new_xp)?;
+        }
+        let D1 = get_D(&new_xp, amp)?;
+        if token_supply == U256::ZERO {
+            ensure!(is_deposit, SynthPoolError::EmptyPool {});
+            ensure!(D1 > D0, SynthPoolError::DNotIncreasing {});
+            return Ok((D1, fees));
+        }
+        if is_deposit {
+            ensure!(D1 > D0, SynthPoolError::DNotIncreasing {});
+        }
+
+        let D2 = if with_fees {
+            let n = U256::from(n_coins);
+            let fee = self.fee.try_mul(n)?.try_div(U256::from(4).try_mul(n.try_sub(U256::from(1))?)?)?;
+            let ys = D0.try_add(D1)? / n;
+            for i in 0..n_coins {
+                let ideal_balance = D1.try_mul(old_balances[i])? / D0;
+                let difference = if ideal_balance > new_balances[i] {
+                    ideal_balance - new_balances[i]
+                } else {
+                    new_balances[i] - ideal_balance
+                };
+                let xs = rates[i].try_mul(old_balances[i].try_add(new_balances[i])?)? / PRECISION;
+                fees[i] = self.scaled_fee(xs, ys, fee)?.try_mul(difference)? / FEE_DENOMINATOR;
+                new_balances[i] = new_balances[i].try_sub(fees[i])?;
+            }
+            get_D(&xp_mem(rates, &new_balances)?, amp)?
+        } else {
+            D1
+        };
+
+        let token_amount = if is_deposit {
+            token_supply.try_mul(D2.try_sub(D0)?)? / D0
+        } else {
+            token_supply.try_mul(D0.try_sub(D2)?)? / D0
+        };
+        Ok((token_amount, fees))
+    }
+
+    /// Coin `i` received for burning `token_amount` LP tokens, as
+    /// (dy after fees, fee charged).
+    pub fn calc_withdraw_one_coin(&self, token_amount: U256, i: usize) -> Result<(U256, U256)> {
+        check_index(i, self.n_coins())?;
+        let total_supply = self.total_supply;
+        ensure!(
+            token_amount > U256::ZERO && token_amount <= total_supply,
+            SynthPoolError::InvalidTokenAmount {
+                amount: token_amount.saturating_to(),
+                total_supply: total_supply.saturating_to(),
+            }
+        );
+        let amp = self.amp;
+        let (rates, xp) = (&self.rates, self.xp()?);
+        let D0 = get_D(&xp, amp)?;
+        let D1 = D0.try_sub(token_amount.try_mul(D0)? / total_supply)?;
+        let new_y = get_y_D(amp, i, &xp, D1)?;
+
+        let mut xp_reduced = xp.clone();
+        let ys = D0.try_add(D1)? / U256::from(2 * xp.len());
+        for j in 0..xp.len() {
+            let xp_scaled = xp[j].try_mul(D1)? / D0;
+            let (dx_expected, xavg) = if j == i {
+                (xp_scaled.try_sub(new_y)?, xp[j].try_add(new_y)? / U256::from(2))
+            } else {
+                (xp[j].try_sub(xp_scaled)?, xp[j])
+            };
+            let dx_fee = self.scaled_fee(xavg, ys, self.fee)?.try_mul(dx_expected)? / FEE_DENOMINATOR;
+            xp_reduced[j] = xp_reduced[j].try_sub(dx_fee)?;
+        }
+
+        let dy = xp_reduced[i]
+            .try_sub(get_y_D(amp, i, &xp_reduced, D1)?)?
+            .try_sub(U256::from(1))?
+            .try_mul(PRECISION)?
+            .try_div(rates[i])?;
+        let dy_0 = xp[i].try_sub(new_y)?.try_mul(PRECISION)?.try_div(rates[i])?;
+        Ok((dy, dy_0.saturating_sub(dy)))
+    }
+
+    /// Value of one LP token in curve units, D / total supply, scaled by 1e18
+    pub fn virtual_price(&self) -> Result<U256> {
+        let D = get_D(&self.xp()?, self.amp)?;
+        D.try_mul(PRECISION)?.try_div(self.total_supply)
+    }
+
+    /// Binary form, every value a 32-byte LE U256: the coin count, then
+    /// balances, rates and coin fees per coin, then amp, fee, admin fee,
+    /// off-peg fee multiplier and total supply
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let scalars = [self.amp, self.fee, self.admin_fee, self.offpeg_fee_multiplier, self.total_supply];
+        let values = core::iter::once(U256::from(self.n_coins()))
+            .chain(self.balances.iter().copied())
+            .chain(self.rates.iter().copied())
+            .chain(self.coin_fees.iter().copied())
+            .chain(scalars);
+        for value in values {
+            data.extend_from_slice(&value.to_le_bytes::<32>());
+        }
+        data
+    }
+
+    /// Inverse of [`PoolState::encode`]
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let words = data.chunks_exact(32);
+        if !words.remainder().is_empty() {
+            return None;
+        }
+        let words: Vec<U256> = words.map(U256::from_le_slice).collect();
+        let n_coins = usize::try_from(*words.first()?).ok()?;
+        if words.len() != 1 + 3 * n_coins + 5 {
+            return None;
+        }
+        let per_coin = |k: usize| words[1 + k * n_coins..1 + (k + 1) * n_coins].to_vec();
+        let scalars = &words[1 + 3 * n_coins..];
+        Some(Self {
+            balances: per_coin(0),
+            rates: per_coin(1),
+            coin_fees: per_coin(2),
+            amp: scalars[0],
+            fee: scalars[1],
+            admin_fee: scalars[2],
+            offpeg_fee_multiplier: scalars[3],
+            total_supply: scalars[4],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(balance_a: u128, balance_b: u128) -> PoolState {
+        PoolState {
+            balances: vec![U256::from(balance_a), U256::from(balance_b)],
+            rates: vec![PRECISION, PRECISION],
+            amp: U256::from(100 * 100),
+            fee: U256::from(4_000_000),
+            coin_fees: vec![U256::from(4_000_000), U256::from(4_000_000)],
+            admin_fee: U256::from(5_000_000_000u64),
+            offpeg_fee_multiplier: U256::ZERO,
+            total_supply: U256::from(balance_a + balance_b),
+        }
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let state = pool(1_000_000_000, 2_000_000_000);
+        assert_eq!(state.encode().len(), 32 * (1 + 3 * 2 + 5));
+        assert_eq!(PoolState::decode(&state.encode()), Some(state.clone()));
+        assert_eq!(PoolState::decode(&state.encode()[1..]), None);
+        assert_eq!(PoolState::decode(&state.encode()[32..]), None);
+    }
+
+    #[test]
+    fn test_quotes() {
+        let state = pool(1_000_000_000, 1_000_000_000);
+        assert_eq!(state.virtual_price(), Ok(PRECISION));
+        assert_eq!(state.spot_price(0, 1), Ok(PRECISION));
+
+        let (dy, fee, admin_fee) = state.get_dy(0, 1, U256::from(1_000_000)).unwrap();
+        assert_eq!(fee, (dy + fee) * U256::from(4_000_000) / FEE_DENOMINATOR);
+        assert_eq!(admin_fee, fee / U256::from(2));
+        let dx = state.get_dx(0, 1, dy).unwrap();
+        assert!(state.get_dy(0, 1, dx).unwrap().0 >= dy);
+        assert!(dx <= U256::from(1_000_002));
+
+        assert_eq!(state.get_dy(0, 0, U256::from(1)), Err(SynthPoolError::SameCoin { index: 0 }));
+        assert_eq!(
+            state.calc_withdraw_one_coin(U256::ZERO, 0),
+            Err(SynthPoolError::InvalidTokenAmount { amount: 0, total_supply: 2_000_000_000 })
+        );
+    }
+
+    #[test]
+    fn test_max_pool_size() {
+        let total = U256::from(MAX_XP_SUM);
+        for amp in [U256::from(100), U256::from(100 * 100), U256::from(10u128.pow(8))] {
+            // eight balanced coins filling the cap
+            let balanced = vec![total / U256::from(8); 8];
+            assert!(get_D(&balanced, amp).unwrap().abs_diff(total) <= U256::from(1));
+
+            // seven coins at a tenth of their balanced share
+            let mut skewed = vec![total / U256::from(8)];
+            skewed.extend(vec![total / U256::from(80); 7]);
+            assert!(get_D(&skewed, amp).is_ok());
+
+            // D_P grows as D**(n+1) / prod(n * x_i), so emptied coins overflow it
+            let mut drained = vec![total / U256::from(6); 6];
+            drained.extend(vec![U256::from(1); 2]);
+            assert_eq!(get_D(&drained, amp), Err(SynthPoolError::MathOverflow {}));
+        }
+    }
+}
@@ -0,0 +1,214 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+use crate::AlkaneId;
+
+/// A calldata argument. A `u128` is one input, an alkane id is two
+/// (block, tx) and a `Vec<u128>` is its length followed by the items.
+trait CallArg: Sized {
+    fn encode(&self, inputs: &mut Vec<u128>);
+    fn decode(args: &mut &[u128]) -> Option<Self>;
+}
+
+impl CallArg for u128 {
+    fn encode(&self, inputs: &mut Vec<u128>) {
+        inputs.push(*self);
+    }
+
+    fn decode(args: &mut &[u128]) -> Option<Self> {
+        let (&value, rest) = args.split_first()?;
+        *args = rest;
+        Some(value)
+    }
+}
+
+impl CallArg for AlkaneId {
+    fn encode(&self, inputs: &mut Vec<u128>) {
+        inputs.extend([self.block, self.tx]);
+    }
+
+    fn decode(args: &mut &[u128]) -> Option<Self> {
+        Some(Self::new(u128::decode(args)?, u128::decode(args)?))
+    }
+}
+
+impl CallArg for Vec<u128> {
+    fn encode(&self, inputs: &mut Vec<u128>) {
+        inputs.push(self.len() as u128);
+        inputs.extend_from_slice(self);
+    }
+
+    fn decode(args: &mut &[u128]) -> Option<Self> {
+        let len = usize::try_from(u128::decode(args)?).ok()?;
+        if len > args.len() {
+            return None;
+        }
+        let (values, rest) = args.split_at(len);
+        *args = rest;
+        Some(values.to_vec())
+    }
+}
+
+macro_rules! synth_pool_calls {
+    ($($(#[$doc:meta])* $opcode:literal $name:ident { $($field:ident: $ty:ty),* },)*) => {
+        /// Every message the synth-pool contract accepts, named and laid out
+        /// like its `SynthPoolMessage`
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub enum SynthPoolCall {
+            $($(#[$doc])* $name { $($field: $ty),* },)*
+        }
+
+        impl SynthPoolCall {
+            pub fn opcode(&self) -> u128 {
+                match self {
+                    $(Self::$name { .. } => $opcode,)*
+                }
+            }
+
+            /// Views answer simulations; every other call changes state and
+            /// goes in a transaction
+            pub fn is_view(&self) -> bool {
+                self.opcode() >= 99
+            }
+
+            /// Cellpack inputs: the opcode followed by the arguments
+            pub fn inputs(&self) -> Vec<u128> {
+                let mut inputs = vec![self.opcode()];
+                match self {
+                    $(Self::$name { $($field),* } => {
+                        $($field.encode(&mut inputs);)*
+                    })*
+                }
+                inputs
+            }
+
+            /// Inverse of [`SynthPoolCall::inputs`], e.g. for indexing calls
+            /// found in transactions
+            pub fn decode(inputs: &[u128]) -> Option<Self> {
+                let (&opcode, mut args) = inputs.split_first()?;
+                let call = match opcode {
+                    $($opcode => Self::$name { $($field: CallArg::decode(&mut args)?),* },)*
+                    _ => return None,
+                };
+                args.is_empty().then_some(call)
+            }
+        }
+    };
+}
+
+synth_pool_calls! {
+    0 InitPool { token_a: AlkaneId, token_b: AlkaneId, A: u128, fee: u128, admin_fee: u128, owner: AlkaneId },
+    1 AddLiquidity { min_mint_amount: u128 },
+    2 RemoveLiquidity { min_amounts: Vec<u128> },
+    3 RemoveLiquidityOneCoin { i: u128, min_amount: u128 },
+    4 RemoveLiquidityImbalance { amounts: Vec<u128>, max_burn_amount: u128 },
+    5 Swap { j: u128, min_dy: u128 },
+    /// `coins` are flattened (block, tx) pairs; empty `coin_fees`, `decimals`
+    /// and `rate_oracles` default to `fee`, 8 and no oracle for every coin
+    6 InitPoolN {
+        coins: Vec<u128>,
+        A: u128,
+        fee: u128,
+        coin_fees: Vec<u128>,
+        decimals: Vec<u128>,
+        rate_oracles: Vec<u128>,
+        admin_fee: u128,
+        owner: AlkaneId
+    },
+    7 SwapExactOut { j: u128, dy: u128, max_dx: u128, deadline: u128 },
+    10 ClaimAdminFees {},
+    11 RampA { future_a: u128, future_height: u128 },
+    12 StopRampA {},
+    13 CommitNewFee { fee: u128, admin_fee: u128, coin_fees: Vec<u128>, offpeg_fee_multiplier: u128 },
+    14 ApplyNewFee {},
+    15 RevertNewParameters {},
+    16 CommitTransferOwnership { new_owner: AlkaneId },
+    17 ApplyTransferOwnership {},
+    18 RevertTransferOwnership {},
+    19 RenounceOwnership {},
+    20 KillMe {},
+    21 UnkillMe {},
+    22 SetOracleHalfLife { half_life: u128 },
+    /// `deadline` is the last block height at which the call may execute; 0 disables it
+    31 AddLiquidityWithDeadline { min_mint_amount: u128, deadline: u128 },
+    32 RemoveLiquidityWithDeadline { min_amounts: Vec<u128>, deadline: u128 },
+    33 RemoveLiquidityOneCoinWithDeadline { i: u128, min_amount: u128, deadline: u128 },
+    34 RemoveLiquidityImbalanceWithDeadline { amounts: Vec<u128>, max_burn_amount: u128, deadline: u128 },
+    35 SwapWithDeadline { j: u128, min_dy: u128, deadline: u128 },
+    50 Forward {},
+    99 GetName {},
+    100 GetSymbol {},
+    101 GetTotalSupply {},
+    /// Was opcode 100, which is now the LP token's GetSymbol; likewise
+    /// GetBalances and GetA moved from 101 and 102
+    200 GetVirtualPrice {},
+    201 GetBalances {},
+    202 GetA {},
+    203 GetPendingParameters {},
+    204 GetOwner {},
+    205 IsKilled {},
+    206 GetDy { i: u128, j: u128, dx: u128 },
+    207 GetDyBreakdown { i: u128, j: u128, dx: u128 },
+    208 GetDx { i: u128, j: u128, dy: u128 },
+    209 GetSpotPrice { i: u128, j: u128 },
+    210 GetPriceImpact { i: u128, j: u128, dx: u128 },
+    211 CalcTokenAmount { amounts: Vec<u128>, is_deposit: u128 },
+    212 CalcTokenAmountWithFees { amounts: Vec<u128>, is_deposit: u128 },
+    213 CalcWithdrawOneCoin { token_amount: u128, i: u128 },
+    214 CalcWithdrawOneCoinWithFee { token_amount: u128, i: u128 },
+    215 PriceOracle { i: u128 },
+    216 LastPrice { i: u128 },
+    217 EmaVirtualPrice {},
+    218 GetOracleHalfLife {},
+    219 GetTwap { window_blocks: u128 },
+    220 GetCoinFees {},
+    221 GetOffpegFeeMultiplier {},
+    222 GetDynamicFee { i: u128, j: u128 },
+    223 GetRates {},
+    224 GetPoolState {},
+    1001 GetDecimals {},
+    1002 GetBalanceOf { owner: AlkaneId },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inputs() {
+        let call = SynthPoolCall::InitPoolN {
+            coins: vec![2, 1, 2, 3],
+            A: 10_000,
+            fee: 4_000_000,
+            coin_fees: vec![],
+            decimals: vec![8, 18],
+            rate_oracles: vec![],
+            admin_fee: 0,
+            owner: AlkaneId::new(2, 5),
+        };
+        assert_eq!(call.inputs(), vec![6, 4, 2, 1, 2, 3, 10_000, 4_000_000, 0, 2, 8, 18, 0, 0, 2, 5]);
+        assert_eq!(SynthPoolCall::decode(&call.inputs()), Some(call));
+        assert_eq!(SynthPoolCall::GetVirtualPrice {}.inputs(), vec![200]);
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed() {
+        assert_eq!(SynthPoolCall::decode(&[]), None);
+        assert_eq!(SynthPoolCall::decode(&[8]), None);
+        assert_eq!(SynthPoolCall::decode(&[5, 1]), None);
+        assert_eq!(SynthPoolCall::decode(&[5, 1, 0, 0]), None);
+        assert_eq!(SynthPoolCall::decode(&[2, u128::MAX]), None);
+    }
+}
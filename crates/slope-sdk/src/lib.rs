@@ -0,0 +1,50 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+//! Client SDK for the synth-pool contract.
+//!
+//! [`SynthPoolCall`] builds the cellpack inputs of every opcode, and the
+//! functions in [`view`] decode the response data of every view. Failed
+//! calls decode with [`SynthPoolError::from_revert_data`], state changes
+//! carry a [`SynthPoolEvent`], and [`PoolState`] quotes locally with the
+//! contract's own maths. [`protostone`] wraps calls in runestones, and with
+//! the `psbt` feature, on by default, [`psbt`] builds unsigned transactions
+//! making them.
+#![allow(non_snake_case)]
+
+mod call;
+pub mod protostone;
+#[cfg(feature = "psbt")]
+pub mod psbt;
+pub mod view;
+
+pub use call::SynthPoolCall;
+pub use ruint::aliases::U256;
+pub use slope_errors::SynthPoolError;
+pub use slope_events::SynthPoolEvent;
+pub use slope_math::{self as math, PoolState};
+
+/// Alkane id as the contract takes it in calldata: two u128 inputs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AlkaneId {
+    pub block: u128,
+    pub tx: u128,
+}
+
+impl AlkaneId {
+    pub fn new(block: u128, tx: u128) -> Self {
+        Self { block, tx }
+    }
+}
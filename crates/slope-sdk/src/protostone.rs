@@ -0,0 +1,304 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+//! Runestones carrying alkanes protostones.
+//!
+//! A runestone payload is a list of LEB128 varints read as (tag, value)
+//! pairs. Protostones ride in its Protocol field: each one is flattened to
+//! `[protocol tag, payload length, payload...]`, the whole list is varint
+//! encoded, and the bytes are cut into 15-byte LE words, one Protocol value
+//! each. A protostone's own payload uses the same (tag, value) scheme, with
+//! its message (the cellpack, varint encoded) in 15-byte words and its
+//! edicts after the body tag.
+
+use crate::AlkaneId;
+
+/// Protocol tag of alkanes protostones
+pub const ALKANES_PROTOCOL_TAG: u128 = 1;
+
+mod tag {
+    pub const BODY: u128 = 0;
+    pub const POINTER: u128 = 22;
+    pub const PROTOCOL: u128 = 16383;
+}
+
+mod protostone_tag {
+    pub const BODY: u128 = 0;
+    pub const MESSAGE: u128 = 81;
+    pub const POINTER: u128 = 91;
+    pub const REFUND: u128 = 93;
+}
+
+pub fn encode_varint(mut value: u128, data: &mut Vec<u8>) {
+    while value >> 7 > 0 {
+        data.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+/// Decodes every varint in `data`
+pub fn decode_varints(data: &[u8]) -> Option<Vec<u128>> {
+    let mut values = vec![];
+    let mut value = 0u128;
+    let mut shift = 0;
+    for &byte in data {
+        if shift > 126 || (shift == 126 && byte & 0x7f > 0b11) {
+            return None;
+        }
+        value |= u128::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            values.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+        }
+    }
+    (shift == 0).then_some(values)
+}
+
+fn encode_varints(values: &[u128]) -> Vec<u8> {
+    let mut data = vec![];
+    for &value in values {
+        encode_varint(value, &mut data);
+    }
+    data
+}
+
+/// Bytes as 15-byte LE words, the last one zero-padded
+fn pack(data: &[u8]) -> Vec<u128> {
+    data.chunks(15)
+        .map(|chunk| {
+            let mut word = [0u8; 16];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u128::from_le_bytes(word)
+        })
+        .collect()
+}
+
+fn unpack(words: &[u128]) -> Option<Vec<u8>> {
+    let mut data = vec![];
+    for word in words {
+        let bytes = word.to_le_bytes();
+        if bytes[15] != 0 {
+            return None;
+        }
+        data.extend_from_slice(&bytes[..15]);
+    }
+    Some(data)
+}
+
+/// Moves `amount` of alkane `id` to output `output`, which may be the
+/// virtual output of a protostone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edict {
+    pub id: AlkaneId,
+    pub amount: u128,
+    pub output: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Protostone {
+    pub protocol_tag: u128,
+    /// The cellpack, varint encoded. Decoding leaves the zero padding of its
+    /// last word in place.
+    pub message: Vec<u8>,
+    pub edicts: Vec<Edict>,
+    /// Output receiving what the call returns
+    pub pointer: Option<u32>,
+    /// Output receiving the incoming alkanes if the call fails
+    pub refund: Option<u32>,
+}
+
+impl Protostone {
+    /// An alkanes call on `target` with cellpack inputs `inputs`
+    pub fn call(target: AlkaneId, inputs: &[u128]) -> Self {
+        let mut cellpack = vec![target.block, target.tx];
+        cellpack.extend_from_slice(inputs);
+        Self {
+            protocol_tag: ALKANES_PROTOCOL_TAG,
+            message: encode_varints(&cellpack),
+            ..Default::default()
+        }
+    }
+
+    /// Cellpack of the message: target block and tx, then the inputs
+    pub fn cellpack(&self) -> Option<Vec<u128>> {
+        decode_varints(&self.message)
+    }
+
+    fn to_integers(&self) -> Vec<u128> {
+        let mut payload = vec![];
+        if let Some(pointer) = self.pointer {
+            payload.extend([protostone_tag::POINTER, pointer.into()]);
+        }
+        if let Some(refund) = self.refund {
+            payload.extend([protostone_tag::REFUND, refund.into()]);
+        }
+        for word in pack(&self.message) {
+            payload.extend([protostone_tag::MESSAGE, word]);
+        }
+        if !self.edicts.is_empty() {
+            payload.push(protostone_tag::BODY);
+            // edict ids are delta encoded against the previous, in id order
+            let mut edicts = self.edicts.clone();
+            edicts.sort_by_key(|edict| edict.id);
+            let mut previous = AlkaneId::default();
+            for edict in edicts {
+                let (block, tx) = if edict.id.block == previous.block {
+                    (0, edict.id.tx - previous.tx)
+                } else {
+                    (edict.id.block - previous.block, edict.id.tx)
+                };
+                payload.extend([block, tx, edict.amount, edict.output.into()]);
+                previous = edict.id;
+            }
+        }
+        let mut integers = vec![self.protocol_tag, payload.len() as u128];
+        integers.extend(payload);
+        integers
+    }
+
+    fn from_payload(protocol_tag: u128, payload: &[u128]) -> Option<Self> {
+        let mut protostone = Self { protocol_tag, ..Default::default() };
+        let mut message = vec![];
+        let mut fields = payload;
+        while let Some((&tag, rest)) = fields.split_first() {
+            if tag == protostone_tag::BODY {
+                let mut previous = AlkaneId::default();
+                for edict in rest.chunks(4) {
+                    let &[block, tx, amount, output] = edict else {
+                        return None;
+                    };
+                    let id = if block == 0 {
+                        AlkaneId::new(previous.block, previous.tx.checked_add(tx)?)
+                    } else {
+                        AlkaneId::new(previous.block.checked_add(block)?, tx)
+                    };
+                    let output = u32::try_from(output).ok()?;
+                    protostone.edicts.push(Edict { id, amount, output });
+                    previous = id;
+                }
+                break;
+            }
+            let (&value, rest) = rest.split_first()?;
+            match tag {
+                protostone_tag::POINTER => protostone.pointer = Some(u32::try_from(value).ok()?),
+                protostone_tag::REFUND => protostone.refund = Some(u32::try_from(value).ok()?),
+                protostone_tag::MESSAGE => message.push(value),
+                _ => {}
+            }
+            fields = rest;
+        }
+        protostone.message = unpack(&message)?;
+        Some(protostone)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Runestone {
+    /// Output receiving the alkanes no edict moves
+    pub pointer: Option<u32>,
+    pub protostones: Vec<Protostone>,
+}
+
+impl Runestone {
+    /// Payload to push after `OP_RETURN OP_13`
+    pub fn encipher(&self) -> Vec<u8> {
+        let mut payload = vec![];
+        if let Some(pointer) = self.pointer {
+            encode_varint(tag::POINTER, &mut payload);
+            encode_varint(pointer.into(), &mut payload);
+        }
+        let integers: Vec<u128> = self.protostones.iter().flat_map(Protostone::to_integers).collect();
+        for word in pack(&encode_varints(&integers)) {
+            encode_varint(tag::PROTOCOL, &mut payload);
+            encode_varint(word, &mut payload);
+        }
+        payload
+    }
+
+    /// Inverse of [`Runestone::encipher`]. Fields other than the pointer
+    /// and protostones are skipped, as are runestone edicts.
+    pub fn decipher(payload: &[u8]) -> Option<Self> {
+        let values = decode_varints(payload)?;
+        let mut runestone = Self::default();
+        let mut protocol = vec![];
+        for field in values.chunks(2) {
+            match *field {
+                [tag::BODY, ..] => break,
+                [tag::POINTER, pointer] => runestone.pointer = Some(u32::try_from(pointer).ok()?),
+                [tag::PROTOCOL, word] => protocol.push(word),
+                [_, _] => {}
+                _ => return None,
+            }
+        }
+
+        let integers = decode_varints(&unpack(&protocol)?)?;
+        let mut rest = &integers[..];
+        // the zero padding of the last word decodes as zeros
+        while let [protocol_tag, len, tail @ ..] = rest {
+            if *protocol_tag == 0 {
+                break;
+            }
+            let len = usize::try_from(*len).ok().filter(|&len| len <= tail.len())?;
+            runestone.protostones.push(Protostone::from_payload(*protocol_tag, &tail[..len])?);
+            rest = &tail[len..];
+        }
+        Some(runestone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varints() {
+        let mut data = vec![];
+        for value in [0, 1, 127, 128, 300, u128::MAX] {
+            encode_varint(value, &mut data);
+        }
+        assert_eq!(&data[..6], [0x00, 0x01, 0x7f, 0x80, 0x01, 0xac]);
+        assert_eq!(decode_varints(&data), Some(vec![0, 1, 127, 128, 300, u128::MAX]));
+        assert_eq!(decode_varints(&[0x80]), None);
+        assert_eq!(decode_varints(&[0xff; 19]), None);
+    }
+
+    #[test]
+    fn test_runestone_round_trip() {
+        let pool = AlkaneId::new(2, 21);
+        let mut protostone = Protostone::call(pool, &[1, 1_000_000, 0]);
+        protostone.pointer = Some(0);
+        protostone.refund = Some(0);
+        protostone.edicts = vec![
+            Edict { id: AlkaneId::new(2, 7), amount: 500, output: 4 },
+            Edict { id: AlkaneId::new(2, 3), amount: u128::MAX, output: 4 },
+        ];
+        let runestone = Runestone { pointer: Some(0), protostones: vec![protostone.clone()] };
+
+        let decoded = Runestone::decipher(&runestone.encipher()).unwrap();
+        assert_eq!(decoded.pointer, Some(0));
+        let [decoded] = &decoded.protostones[..] else { panic!("expected one protostone") };
+        assert_eq!((decoded.pointer, decoded.refund), (Some(0), Some(0)));
+        assert_eq!(decoded.edicts, [protostone.edicts[1], protostone.edicts[0]]);
+        let cellpack = decoded.cellpack().unwrap();
+        assert_eq!(cellpack[..5], [2, 21, 1, 1_000_000, 0]);
+        assert!(cellpack[5..].iter().all(|&v| v == 0));
+
+        assert_eq!(Runestone::decipher(&[22]), None);
+    }
+}
@@ -0,0 +1,337 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+//! Unsigned PSBTs for pool calls.
+//!
+//! The transaction spends the UTXOs holding the alkanes the call consumes,
+//! plus plain UTXOs for the fee, and has three outputs: the recipient, which
+//! gets what the call returns, refunds and every alkane left over; the
+//! runestone; and BTC change, dropped when it would be dust. The protostone's
+//! edicts move exactly the consumed alkanes from the inputs into the call.
+//! Coin selection is first-fit in the order the UTXOs are given, and never
+//! spends a UTXO holding alkanes just to pay the fee.
+
+use std::fmt;
+
+use bitcoin::{
+    absolute::LockTime,
+    opcodes::all::{OP_PUSHNUM_13, OP_RETURN},
+    psbt::Psbt,
+    script::{Builder, Instruction, PushBytes},
+    transaction::Version,
+    Amount, FeeRate, OutPoint, Script, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Weight, Witness,
+};
+
+use crate::protostone::{Edict, Protostone, Runestone};
+use crate::{AlkaneId, SynthPoolCall};
+
+/// Value of the recipient and change outputs' floor, the taproot dust limit
+pub const DUST_LIMIT: Amount = Amount::from_sat(546);
+/// Witness of a key-path taproot spend: item count, length and signature
+const INPUT_WITNESS_WEIGHT: u64 = 1 + 1 + 64;
+/// Segwit marker and flag
+const SEGWIT_HEADER_WEIGHT: u64 = 2;
+/// Largest data push a script may hold
+const MAX_PUSH_SIZE: usize = 520;
+
+const RECIPIENT_VOUT: u32 = 0;
+
+/// A UTXO the builder may spend, with the alkanes it holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Utxo {
+    pub outpoint: OutPoint,
+    pub txout: TxOut,
+    pub alkanes: Vec<(AlkaneId, u128)>,
+}
+
+impl Utxo {
+    fn holds(&self, id: AlkaneId) -> u128 {
+        self.alkanes.iter().filter(|(held, _)| *held == id).map(|(_, amount)| amount).sum()
+    }
+}
+
+#[derive(Debug)]
+pub enum TxError {
+    /// Views are read by simulating them, not by a transaction
+    UnsupportedCall { opcode: u128 },
+    InsufficientAlkane { id: AlkaneId, needed: u128, available: u128 },
+    InsufficientFunds { needed: Amount, available: Amount },
+    Psbt(bitcoin::psbt::Error),
+}
+
+impl fmt::Display for TxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedCall { opcode } => write!(f, "Opcode {} is a view, simulate it instead", opcode),
+            Self::InsufficientAlkane { id, needed, available } => write!(
+                f,
+                "Need {} of alkane {}:{}, the UTXOs hold {}",
+                needed, id.block, id.tx, available
+            ),
+            Self::InsufficientFunds { needed, available } => {
+                write!(f, "Need {} including fees, the UTXOs hold {}", needed, available)
+            }
+            Self::Psbt(err) => write!(f, "PSBT: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for TxError {}
+
+/// Builds pool calls for one wallet
+#[derive(Debug, Clone)]
+pub struct TxBuilder {
+    pub pool: AlkaneId,
+    /// UTXOs available to spend, in order of preference
+    pub utxos: Vec<Utxo>,
+    pub recipient: ScriptBuf,
+    pub change: ScriptBuf,
+    pub fee_rate: FeeRate,
+}
+
+impl TxBuilder {
+    /// Unsigned PSBT making `call`, which consumes `transfers`: the coin sold
+    /// by a swap, the coins deposited by AddLiquidity, the LP tokens burned by
+    /// a RemoveLiquidity call, or nothing for the admin calls.
+    pub fn build(&self, call: &SynthPoolCall, transfers: &[(AlkaneId, u128)]) -> Result<Psbt, TxError> {
+        if call.is_view() {
+            return Err(TxError::UnsupportedCall { opcode: call.opcode() });
+        }
+
+        let mut selected = self.select_alkanes(transfers)?;
+        loop {
+            let inputs: Amount = selected.iter().map(|&i| self.utxos[i].txout.value).sum();
+            // with change, then without, which hands the remainder to the fee
+            for with_change in [true, false] {
+                let tx = self.transaction(call, transfers, &selected, with_change.then_some(Amount::ZERO));
+                let fee = self.fee(&tx);
+                let spent = DUST_LIMIT + fee;
+                if with_change && inputs >= spent + DUST_LIMIT {
+                    return self.psbt(self.transaction(call, transfers, &selected, Some(inputs - spent)), &selected);
+                }
+                if !with_change && inputs >= spent {
+                    return self.psbt(tx, &selected);
+                }
+            }
+            let next = (0..self.utxos.len()).find(|i| !selected.contains(i) && self.utxos[*i].alkanes.is_empty());
+            match next {
+                Some(i) => selected.push(i),
+                None => {
+                    let tx = self.transaction(call, transfers, &selected, None);
+                    return Err(TxError::InsufficientFunds { needed: DUST_LIMIT + self.fee(&tx), available: inputs });
+                }
+            }
+        }
+    }
+
+    /// Indices of the UTXOs holding the alkanes in `transfers`
+    fn select_alkanes(&self, transfers: &[(AlkaneId, u128)]) -> Result<Vec<usize>, TxError> {
+        let mut selected: Vec<usize> = vec![];
+        for &(id, needed) in transfers {
+            let held = |selected: &[usize]| selected.iter().map(|&i| self.utxos[i].holds(id)).sum::<u128>();
+            for (i, utxo) in self.utxos.iter().enumerate() {
+                if held(&selected) >= needed {
+                    break;
+                }
+                if utxo.holds(id) > 0 && !selected.contains(&i) {
+                    selected.push(i);
+                }
+            }
+            if held(&selected) < needed {
+                let available = self.utxos.iter().map(|utxo| utxo.holds(id)).sum();
+                return Err(TxError::InsufficientAlkane { id, needed, available });
+            }
+        }
+        Ok(selected)
+    }
+
+    fn transaction(
+        &self,
+        call: &SynthPoolCall,
+        transfers: &[(AlkaneId, u128)],
+        selected: &[usize],
+        change: Option<Amount>,
+    ) -> Transaction {
+        let mut output = vec![
+            TxOut { value: DUST_LIMIT, script_pubkey: self.recipient.clone() },
+            TxOut { value: Amount::ZERO, script_pubkey: ScriptBuf::new() },
+        ];
+        if let Some(value) = change {
+            output.push(TxOut { value, script_pubkey: self.change.clone() });
+        }
+        // protostones take the virtual outputs after the real ones and the
+        // one past them
+        let protostone_vout = output.len() as u32 + 1;
+        let mut protostone = Protostone::call(self.pool, &call.inputs());
+        protostone.edicts = transfers
+            .iter()
+            .map(|&(id, amount)| Edict { id, amount, output: protostone_vout })
+            .collect();
+        protostone.pointer = Some(RECIPIENT_VOUT);
+        protostone.refund = Some(RECIPIENT_VOUT);
+        let runestone = Runestone { pointer: Some(RECIPIENT_VOUT), protostones: vec![protostone] };
+        output[1].script_pubkey = runestone_script(&runestone);
+
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: selected
+                .iter()
+                .map(|&i| TxIn {
+                    previous_output: self.utxos[i].outpoint,
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output,
+        }
+    }
+
+    /// Fee of `tx` once every input carries a key-path taproot witness
+    fn fee(&self, tx: &Transaction) -> Amount {
+        let witness = Weight::from_wu(SEGWIT_HEADER_WEIGHT + INPUT_WITNESS_WEIGHT * tx.input.len() as u64);
+        self.fee_rate.fee_wu(tx.weight() + witness).unwrap_or(Amount::MAX_MONEY)
+    }
+
+    fn psbt(&self, tx: Transaction, selected: &[usize]) -> Result<Psbt, TxError> {
+        let mut psbt = Psbt::from_unsigned_tx(tx).map_err(TxError::Psbt)?;
+        for (input, &i) in psbt.inputs.iter_mut().zip(selected) {
+            input.witness_utxo = Some(self.utxos[i].txout.clone());
+        }
+        Ok(psbt)
+    }
+}
+
+/// `OP_RETURN OP_13` followed by the runestone payload
+pub fn runestone_script(runestone: &Runestone) -> ScriptBuf {
+    let mut builder = Builder::new().push_opcode(OP_RETURN).push_opcode(OP_PUSHNUM_13);
+    for chunk in runestone.encipher().chunks(MAX_PUSH_SIZE) {
+        let push: &PushBytes = chunk.try_into().expect("chunks fit in a push");
+        builder = builder.push_slice(push);
+    }
+    builder.into_script()
+}
+
+/// The runestone in an output script, if it holds one
+pub fn decode_runestone_script(script: &Script) -> Option<Runestone> {
+    let mut instructions = script.instructions();
+    if instructions.next()?.ok()? != Instruction::Op(OP_RETURN)
+        || instructions.next()?.ok()? != Instruction::Op(OP_PUSHNUM_13)
+    {
+        return None;
+    }
+    let mut payload = vec![];
+    for instruction in instructions {
+        match instruction.ok()? {
+            Instruction::PushBytes(push) => payload.extend_from_slice(push.as_bytes()),
+            Instruction::Op(_) => return None,
+        }
+    }
+    Runestone::decipher(&payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Txid;
+    use std::str::FromStr;
+
+    const TOKEN_A: AlkaneId = AlkaneId { block: 2, tx: 1 };
+    const TOKEN_B: AlkaneId = AlkaneId { block: 2, tx: 3 };
+    const POOL: AlkaneId = AlkaneId { block: 2, tx: 21 };
+
+    fn p2tr(byte: u8) -> ScriptBuf {
+        ScriptBuf::from_hex(&format!("5120{}", format!("{:02x}", byte).repeat(32))).unwrap()
+    }
+
+    fn utxo(txid_byte: u8, vout: u32, sats: u64, alkanes: Vec<(AlkaneId, u128)>) -> Utxo {
+        Utxo {
+            outpoint: OutPoint::new(Txid::from_str(&format!("{:02x}", txid_byte).repeat(32)).unwrap(), vout),
+            txout: TxOut { value: Amount::from_sat(sats), script_pubkey: p2tr(0xaa) },
+            alkanes,
+        }
+    }
+
+    /// A wallet holding two pool coins, some LP tokens and plain BTC
+    fn fixture() -> TxBuilder {
+        TxBuilder {
+            pool: POOL,
+            utxos: vec![
+                utxo(0x01, 0, 546, vec![(TOKEN_A, 600_000_000), (TOKEN_B, 50_000_000)]),
+                utxo(0x02, 1, 546, vec![(TOKEN_A, 400_000_000)]),
+                utxo(0x03, 0, 546, vec![(POOL, 1_000_000_000)]),
+                utxo(0x04, 2, 10_000, vec![]),
+                utxo(0x05, 0, 100_000, vec![]),
+            ],
+            recipient: p2tr(0xbb),
+            change: p2tr(0xcc),
+            fee_rate: FeeRate::from_sat_per_vb(5).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_swap_psbt() {
+        let builder = fixture();
+        let call = SynthPoolCall::SwapWithDeadline { j: 1, min_dy: 700_000_000, deadline: 0 };
+        let psbt = builder.build(&call, &[(TOKEN_A, 800_000_000)]).unwrap();
+        let tx = &psbt.unsigned_tx;
+
+        // both token_a UTXOs, then plain BTC until the fee is covered
+        let spent: Vec<OutPoint> = [0, 1, 3].iter().map(|&i| builder.utxos[i].outpoint).collect();
+        assert_eq!(tx.input.iter().map(|input| input.previous_output).collect::<Vec<_>>(), spent);
+        assert!(psbt.inputs.iter().all(|input| input.witness_utxo.is_some()));
+
+        assert_eq!(tx.output.len(), 3);
+        assert_eq!(tx.output[0], TxOut { value: DUST_LIMIT, script_pubkey: builder.recipient.clone() });
+        assert_eq!(tx.output[2].script_pubkey, builder.change);
+        let inputs: Amount = psbt.inputs.iter().map(|input| input.witness_utxo.as_ref().unwrap().value).sum();
+        let fee = inputs - tx.output[0].value - tx.output[2].value;
+        assert_eq!(fee, builder.fee(tx));
+
+        let runestone = decode_runestone_script(&tx.output[1].script_pubkey).unwrap();
+        assert_eq!(runestone.pointer, Some(0));
+        let protostone = &runestone.protostones[0];
+        assert_eq!(protostone.edicts, [Edict { id: TOKEN_A, amount: 800_000_000, output: 4 }]);
+        assert_eq!((protostone.pointer, protostone.refund), (Some(0), Some(0)));
+        let cellpack = protostone.cellpack().unwrap();
+        assert_eq!(cellpack[..6], [2, 21, 35, 1, 700_000_000, 0]);
+    }
+
+    #[test]
+    fn test_liquidity_psbt() {
+        let builder = fixture();
+        let call = SynthPoolCall::AddLiquidity { min_mint_amount: 0 };
+        let psbt = builder.build(&call, &[(TOKEN_A, 100_000_000), (TOKEN_B, 50_000_000)]).unwrap();
+        let runestone = decode_runestone_script(&psbt.unsigned_tx.output[1].script_pubkey).unwrap();
+        assert_eq!(runestone.protostones[0].edicts.len(), 2);
+        assert_eq!(psbt.unsigned_tx.input[0].previous_output, builder.utxos[0].outpoint);
+
+        let call = SynthPoolCall::RemoveLiquidity { min_amounts: vec![0, 0] };
+        let psbt = builder.build(&call, &[(POOL, 1_000_000_000)]).unwrap();
+        assert_eq!(psbt.unsigned_tx.input[0].previous_output, builder.utxos[2].outpoint);
+
+        assert!(matches!(
+            builder.build(&call, &[(POOL, 1_000_000_001)]),
+            Err(TxError::InsufficientAlkane { id: POOL, needed: 1_000_000_001, available: 1_000_000_000 })
+        ));
+        assert!(matches!(
+            builder.build(&SynthPoolCall::GetDy { i: 0, j: 1, dx: 1 }, &[]),
+            Err(TxError::UnsupportedCall { opcode: 206 })
+        ));
+        let broke = TxBuilder { fee_rate: FeeRate::from_sat_per_vb(1_000).unwrap(), ..builder };
+        assert!(matches!(broke.build(&call, &[(POOL, 1)]), Err(TxError::InsufficientFunds { .. })));
+    }
+}
@@ -0,0 +1,292 @@
+/*
+ * This file is part of the slope-ski project.
+ *
+ * The slope-ski project is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License.
+ *
+ * The slope-ski project is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * MIT License for more details.
+ *
+ * You should have received a copy of the MIT License
+ * along with the slope-ski project. If not, see <https://opensource.org/licenses/MIT>.
+ */
+
+//! Decoders for the response data of the synth-pool views, one per view,
+//! named after it. Amounts are u128 LE. Balances, A and the virtual price
+//! are U256 LE. Per-coin lists are not length-prefixed; they run to the end
+//! of the data, or split it evenly when a view returns two of them. Every
+//! decoder returns `None` on data of the wrong length.
+
+use crate::{AlkaneId, PoolState, U256};
+
+/// Splits response data into fixed-size words
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (value, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(value)
+    }
+
+    fn u128(&mut self) -> Option<u128> {
+        Some(u128::from_le_bytes(self.take(16)?.try_into().unwrap()))
+    }
+
+    fn u256(&mut self) -> Option<U256> {
+        Some(U256::from_le_slice(self.take(32)?))
+    }
+
+    /// Every remaining u128
+    fn u128s(mut self) -> Option<Vec<u128>> {
+        if !self.0.len().is_multiple_of(16) {
+            return None;
+        }
+        (0..self.0.len() / 16).map(|_| self.u128()).collect()
+    }
+
+    fn end<T>(self, value: T) -> Option<T> {
+        self.0.is_empty().then_some(value)
+    }
+}
+
+fn single_u128(data: &[u8]) -> Option<u128> {
+    let mut reader = Reader(data);
+    let value = reader.u128()?;
+    reader.end(value)
+}
+
+fn single_u256(data: &[u8]) -> Option<U256> {
+    let mut reader = Reader(data);
+    let value = reader.u256()?;
+    reader.end(value)
+}
+
+macro_rules! u128_views {
+    ($($(#[$doc:meta])* $name:ident,)*) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(data: &[u8]) -> Option<u128> {
+                single_u128(data)
+            }
+        )*
+    };
+}
+
+u128_views! {
+    /// LP token supply
+    get_total_supply,
+    /// Output of a swap after fees
+    get_dy,
+    /// Input a swap needs for an exact output after fees
+    get_dx,
+    /// Marginal price of coin i in units of coin j, scaled by 1e18
+    get_spot_price,
+    /// LP tokens minted or burned, ignoring fees
+    calc_token_amount,
+    /// Coin received for burning LP tokens, after fees
+    calc_withdraw_one_coin,
+    /// EMA price of coin i in units of coin 0, scaled by 1e18
+    price_oracle,
+    /// Spot price of coin i in units of coin 0 after the last state change, scaled by 1e18
+    last_price,
+    /// EMA of the virtual price, scaled by 1e18
+    ema_virtual_price,
+    /// Oracle EMA half-life in blocks
+    get_oracle_half_life,
+    get_offpeg_fee_multiplier,
+    /// Swap fee currently charged on swapping coin i for coin j
+    get_dynamic_fee,
+    /// LP token decimals
+    get_decimals,
+    /// LP tokens held by an owner
+    get_balance_of,
+}
+
+pub fn get_name(data: &[u8]) -> Option<String> {
+    String::from_utf8(data.to_vec()).ok()
+}
+
+pub fn get_symbol(data: &[u8]) -> Option<String> {
+    String::from_utf8(data.to_vec()).ok()
+}
+
+/// Value of one LP token, D / total supply, scaled by 1e18
+pub fn get_virtual_price(data: &[u8]) -> Option<U256> {
+    single_u256(data)
+}
+
+/// Amplification coefficient, in A_PRECISION units
+pub fn get_a(data: &[u8]) -> Option<U256> {
+    single_u256(data)
+}
+
+/// Balance of every coin owed to LPs, excluding the admin fees awaiting a claim
+pub fn get_balances(data: &[u8]) -> Option<Vec<U256>> {
+    if !data.len().is_multiple_of(32) {
+        return None;
+    }
+    Some(data.chunks_exact(32).map(U256::from_le_slice).collect())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingParameters {
+    pub fee: u128,
+    pub admin_fee: u128,
+    /// Height from which the commit can be applied, or 0 if none is pending
+    pub admin_actions_deadline: u128,
+    pub offpeg_fee_multiplier: u128,
+    pub coin_fees: Vec<u128>,
+}
+
+/// Fee parameters committed by CommitNewFee
+pub fn get_pending_parameters(data: &[u8]) -> Option<PendingParameters> {
+    let mut reader = Reader(data);
+    Some(PendingParameters {
+        fee: reader.u128()?,
+        admin_fee: reader.u128()?,
+        admin_actions_deadline: reader.u128()?,
+        offpeg_fee_multiplier: reader.u128()?,
+        coin_fees: reader.u128s()?,
+    })
+}
+
+pub fn get_owner(data: &[u8]) -> Option<AlkaneId> {
+    let mut reader = Reader(data);
+    let owner = AlkaneId::new(reader.u128()?, reader.u128()?);
+    reader.end(owner)
+}
+
+pub fn is_killed(data: &[u8]) -> Option<bool> {
+    match single_u128(data)? {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DyBreakdown {
+    /// Output after fees
+    pub dy: u128,
+    /// Output before fees
+    pub dy_gross: u128,
+    /// Share of the fee left in the pool for LPs
+    pub lp_fee: u128,
+    /// Share of the fee kept for the admin
+    pub admin_fee: u128,
+}
+
+pub fn get_dy_breakdown(data: &[u8]) -> Option<DyBreakdown> {
+    let mut reader = Reader(data);
+    let breakdown = DyBreakdown {
+        dy: reader.u128()?,
+        dy_gross: reader.u128()?,
+        lp_fee: reader.u128()?,
+        admin_fee: reader.u128()?,
+    };
+    reader.end(breakdown)
+}
+
+/// Prices of a swap, all scaled by 1e18
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceImpact {
+    pub spot_price: u128,
+    pub execution_price: u128,
+    pub impact: u128,
+}
+
+pub fn get_price_impact(data: &[u8]) -> Option<PriceImpact> {
+    let mut reader = Reader(data);
+    let impact = PriceImpact {
+        spot_price: reader.u128()?,
+        execution_price: reader.u128()?,
+        impact: reader.u128()?,
+    };
+    reader.end(impact)
+}
+
+/// LP tokens minted or burned, and the imbalance fee charged on each coin
+pub fn calc_token_amount_with_fees(data: &[u8]) -> Option<(u128, Vec<u128>)> {
+    let mut reader = Reader(data);
+    Some((reader.u128()?, reader.u128s()?))
+}
+
+/// Coin received for burning LP tokens after fees, and the fee charged
+pub fn calc_withdraw_one_coin_with_fee(data: &[u8]) -> Option<(u128, u128)> {
+    let mut reader = Reader(data);
+    let value = (reader.u128()?, reader.u128()?);
+    reader.end(value)
+}
+
+/// Time-weighted averages over a window, by coin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Twap {
+    /// Prices in units of coin 0, scaled by 1e18
+    pub prices: Vec<u128>,
+    pub balances: Vec<u128>,
+}
+
+pub fn get_twap(data: &[u8]) -> Option<Twap> {
+    let mut values = Reader(data).u128s()?;
+    if !values.len().is_multiple_of(2) {
+        return None;
+    }
+    let balances = values.split_off(values.len() / 2);
+    Some(Twap { prices: values, balances })
+}
+
+/// Swap fee charged on each output coin
+pub fn get_coin_fees(data: &[u8]) -> Option<Vec<u128>> {
+    Reader(data).u128s()
+}
+
+/// Rate of each coin scaled by 1e18
+pub fn get_rates(data: &[u8]) -> Option<Vec<u128>> {
+    Reader(data).u128s()
+}
+
+/// Everything quotes read, ready to quote with locally
+pub fn get_pool_state(data: &[u8]) -> Option<PoolState> {
+    PoolState::decode(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lengths() {
+        assert_eq!(get_dy(&7u128.to_le_bytes()), Some(7));
+        assert_eq!(get_dy(&[0; 15]), None);
+        assert_eq!(get_dy(&[0; 32]), None);
+        assert_eq!(get_virtual_price(&U256::from(5).to_le_bytes::<32>()), Some(U256::from(5)));
+        assert_eq!(get_balances(&[0; 64]), Some(vec![U256::ZERO; 2]));
+        assert_eq!(get_balances(&[0; 48]), None);
+        assert_eq!(is_killed(&2u128.to_le_bytes()), None);
+    }
+
+    #[test]
+    fn test_lists() {
+        let data: Vec<u8> = [1u128, 2, 3, 4, 5, 6].iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(
+            get_pending_parameters(&data[..]),
+            Some(PendingParameters {
+                fee: 1,
+                admin_fee: 2,
+                admin_actions_deadline: 3,
+                offpeg_fee_multiplier: 4,
+                coin_fees: vec![5, 6],
+            })
+        );
+        assert_eq!(get_twap(&data[16..80]), Some(Twap { prices: vec![2, 3], balances: vec![4, 5] }));
+        assert_eq!(get_twap(&data[16..]), None);
+        assert_eq!(calc_token_amount_with_fees(&data[..48]), Some((1, vec![2, 3])));
+        assert_eq!(get_owner(&data[..32]), Some(AlkaneId::new(1, 2)));
+    }
+}